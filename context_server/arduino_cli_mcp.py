@@ -0,0 +1,565 @@
+"""Model Context Protocol server exposing arduino-cli to Zed's assistant.
+
+Speaks JSON-RPC over stdio and only depends on the Python standard library.
+The arduino-cli executable is taken from the ARDUINO_CLI environment
+variable, falling back to the one on the PATH.
+"""
+
+import json
+import os
+import re
+import subprocess
+import sys
+import threading
+import time
+from collections import deque
+
+PROTOCOL_VERSION = "2024-11-05"
+ARDUINO_CLI = os.environ.get("ARDUINO_CLI") or "arduino-cli"
+
+# Port settings such as `baudrate=115200,dtr=off` and the line ending of sent
+# text, from the `monitor` settings of the context server
+MONITOR_CONFIG = [
+    config for config in os.environ.get("ARDUINO_MONITOR_CONFIG", "").split(",") if config
+]
+LINE_ENDING = os.environ.get("ARDUINO_MONITOR_LINE_ENDING", "")
+
+# Number of lines of serial output kept per monitored port.
+SERIAL_BUFFER_LINES = 1000
+
+# Upper bound for a single arduino-cli invocation, in seconds. Compiling a
+# big core from scratch can take several minutes.
+COMMAND_TIMEOUT = 15 * 60
+
+
+class ToolError(Exception):
+    """An error reported back to the model as a failed tool call."""
+
+
+class RequestError(Exception):
+    """An error answering a request, sent back with its JSON-RPC error code."""
+
+    code = -32603
+
+
+class MethodNotFound(RequestError):
+    """A request for a method the server doesn't implement."""
+
+    code = -32601
+
+
+class InvalidParams(RequestError):
+    """A request with parameters the method can't accept, like an unknown tool."""
+
+    code = -32602
+
+
+class ResourceNotFound(RequestError):
+    """A request for a resource the server doesn't have, as MCP defines it."""
+
+    code = -32002
+
+
+def run_cli(*args, timeout=COMMAND_TIMEOUT):
+    """Runs arduino-cli and returns its combined output, raising on failure."""
+    returncode, output = run_cli_unchecked(*args, timeout=timeout)
+    if returncode != 0:
+        raise ToolError(output or f"arduino-cli exited with status {returncode}")
+    return output
+
+
+def run_cli_unchecked(*args, timeout=COMMAND_TIMEOUT):
+    """Runs arduino-cli and returns its exit status and combined output."""
+    try:
+        result = subprocess.run(
+            [ARDUINO_CLI, *args],
+            stdin=subprocess.DEVNULL,
+            capture_output=True,
+            text=True,
+            timeout=timeout,
+        )
+    except FileNotFoundError:
+        raise ToolError(f"arduino-cli not found (tried {ARDUINO_CLI!r})")
+    except subprocess.TimeoutExpired:
+        raise ToolError(f"`arduino-cli {' '.join(args)}` timed out")
+
+    return result.returncode, (result.stdout + result.stderr).strip()
+
+
+def fqbn_args(arguments):
+    fqbn = arguments.get("fqbn")
+    return ["--fqbn", fqbn] if fqbn else []
+
+
+def list_boards(arguments):
+    return run_cli("board", "list")
+
+
+def list_installed_boards(arguments):
+    return run_cli("board", "listall", *filter(None, [arguments.get("filter")]))
+
+
+# `file:line:column: severity: message` lines of GCC's output
+DIAGNOSTIC = re.compile(r"^(.+?):(\d+):(\d+): (fatal error|error|warning|note): (.*)$")
+
+# The log and diagnostics of the latest compile, served as resources
+LATEST_BUILD = {}
+
+
+def parse_diagnostics(output):
+    diagnostics = []
+    for line in output.splitlines():
+        match = DIAGNOSTIC.match(line.strip())
+        if match is None:
+            continue
+        path, line_number, column, severity, message = match.groups()
+        diagnostic = {
+            "file": path,
+            "line": int(line_number),
+            "column": int(column),
+            "severity": severity,
+            "message": message,
+        }
+        # The same error is often reported once per including file
+        if diagnostic not in diagnostics:
+            diagnostics.append(diagnostic)
+    return diagnostics
+
+
+def compile_sketch(arguments):
+    sketch_path = arguments["sketch_path"]
+    returncode, output = run_cli_unchecked("compile", *fqbn_args(arguments), sketch_path)
+    LATEST_BUILD.update(
+        sketch_path=sketch_path,
+        fqbn=arguments.get("fqbn"),
+        success=returncode == 0,
+        time=time.strftime("%Y-%m-%d %H:%M:%S"),
+        log=output,
+        diagnostics=parse_diagnostics(output),
+    )
+    if returncode != 0:
+        raise ToolError(output or f"arduino-cli exited with status {returncode}")
+    return output
+
+
+def upload_sketch(arguments):
+    return run_cli(
+        "upload",
+        "--port",
+        arguments["port"],
+        *fqbn_args(arguments),
+        arguments["sketch_path"],
+    )
+
+
+def lib_search(arguments):
+    return run_cli("lib", "search", arguments["query"])
+
+
+def lib_install(arguments):
+    return run_cli("lib", "install", arguments["name"])
+
+
+def core_list(arguments):
+    return run_cli("core", "list")
+
+
+def core_search(arguments):
+    return run_cli("core", "search", arguments["query"])
+
+
+def core_install(arguments):
+    return run_cli("core", "install", arguments["platform"])
+
+
+def monitor_args(arguments):
+    config = MONITOR_CONFIG
+    if "baudrate" in arguments:
+        config = [c for c in config if not c.startswith("baudrate=")]
+        config.append(f"baudrate={arguments['baudrate']}")
+    args = [ARDUINO_CLI, "monitor", "--port", arguments["port"], "--quiet"]
+    for setting in config:
+        args += ["--config", setting]
+    return args
+
+
+class SerialMonitor:
+    """Runs `arduino-cli monitor` in the background, keeping its latest lines."""
+
+    def __init__(self, arguments):
+        self.port = arguments["port"]
+        self.lines = deque(maxlen=SERIAL_BUFFER_LINES)
+        self.lock = threading.Lock()
+        try:
+            self.process = subprocess.Popen(
+                monitor_args(arguments),
+                stdin=subprocess.PIPE,
+                stdout=subprocess.PIPE,
+                stderr=subprocess.STDOUT,
+                text=True,
+                errors="replace",
+            )
+        except FileNotFoundError:
+            raise ToolError(f"arduino-cli not found (tried {ARDUINO_CLI!r})")
+        threading.Thread(target=self.read, daemon=True).start()
+
+    def read(self):
+        for line in self.process.stdout:
+            with self.lock:
+                self.lines.append(line.rstrip("\r\n"))
+
+    def recent(self, count):
+        with self.lock:
+            lines = list(self.lines)[-count:] if count > 0 else []
+        status = "" if self.process.poll() is None else " (monitor exited)"
+        if not lines:
+            return f"No serial output from {self.port} yet{status}."
+        return "\n".join(lines) + (f"\n{status.strip()}" if status else "")
+
+    def send(self, text):
+        if self.process.poll() is not None:
+            raise ToolError(f"the monitor of {self.port} exited")
+        self.process.stdin.write(text + LINE_ENDING)
+        self.process.stdin.flush()
+
+    def stop(self):
+        self.process.terminate()
+        try:
+            self.process.wait(timeout=5)
+        except subprocess.TimeoutExpired:
+            self.process.kill()
+
+
+# Running serial monitors by port
+MONITORS = {}
+
+
+def serial_start(arguments):
+    port = arguments["port"]
+    monitor = MONITORS.get(port)
+    if monitor is not None and monitor.process.poll() is None:
+        return f"Already monitoring {port}."
+    MONITORS[port] = SerialMonitor(arguments)
+    return f"Monitoring {port}. Read its output with serial_read or the serial://{port} resource."
+
+
+def serial_read(arguments):
+    port = arguments["port"]
+    monitor = MONITORS.get(port)
+    if monitor is None:
+        raise ToolError(f"{port} is not monitored, start monitoring it with serial_start")
+    return monitor.recent(int(arguments.get("lines", 50)))
+
+
+def serial_send(arguments):
+    port = arguments["port"]
+    monitor = MONITORS.get(port)
+    if monitor is None:
+        raise ToolError(f"{port} is not monitored, start monitoring it with serial_start")
+    monitor.send(arguments["text"])
+    return f"Sent {arguments['text']!r} to {port}."
+
+
+def serial_stop(arguments):
+    port = arguments["port"]
+    monitor = MONITORS.pop(port, None)
+    if monitor is None:
+        return f"{port} was not monitored."
+    monitor.stop()
+    return f"Stopped monitoring {port}."
+
+
+def monitor_snapshot(arguments):
+    """Captures the serial output of a board for a few seconds."""
+    if arguments.get("port") in MONITORS:
+        # The port can't be opened twice, use the running monitor instead
+        return serial_read(arguments)
+    seconds = min(float(arguments.get("seconds", 5)), 60)
+    try:
+        process = subprocess.Popen(
+            monitor_args(arguments),
+            stdin=subprocess.PIPE,
+            stdout=subprocess.PIPE,
+            stderr=subprocess.STDOUT,
+            text=True,
+            errors="replace",
+        )
+    except FileNotFoundError:
+        raise ToolError(f"arduino-cli not found (tried {ARDUINO_CLI!r})")
+
+    time.sleep(seconds)
+    process.terminate()
+    try:
+        output, _ = process.communicate(timeout=5)
+    except subprocess.TimeoutExpired:
+        process.kill()
+        output, _ = process.communicate()
+    return output.strip() or f"No serial output within {seconds:g} seconds."
+
+
+def detect_board():
+    """Returns the port and FQBN of the first connected board arduino-cli recognizes."""
+    detected = json.loads(run_cli("board", "list", "--format", "json") or "[]")
+    # Newer arduino-cli versions wrap the list in a `detected_ports` object
+    if isinstance(detected, dict):
+        detected = detected.get("detected_ports", [])
+    for detected_port in detected:
+        boards = detected_port.get("matching_boards") or []
+        if boards:
+            return detected_port["port"]["address"], boards[0].get("fqbn")
+    raise ToolError("no board connected")
+
+
+def flash(arguments):
+    """Compiles and uploads a sketch, then captures the board's first serial output."""
+    sketch_path = arguments["sketch_path"]
+    port = arguments.get("port")
+    fqbn = arguments.get("fqbn")
+    steps = []
+    if not port:
+        port, detected_fqbn = detect_board()
+        fqbn = fqbn or detected_fqbn
+        steps.append(f"Detected board on {port} ({detected_fqbn}).")
+
+    compile_output = compile_sketch({"sketch_path": sketch_path, "fqbn": fqbn})
+    steps.append(f"Compiled:\n{compile_output}")
+
+    # The upload needs the port, so release it from a background monitor
+    monitored = MONITORS.pop(port, None)
+    if monitored is not None:
+        monitored.stop()
+    upload_output = run_cli("upload", "--port", port, *fqbn_args({"fqbn": fqbn}), sketch_path)
+    steps.append(f"Uploaded to {port}:\n{upload_output}".rstrip())
+
+    serial_output = monitor_snapshot(
+        {"port": port, "seconds": arguments.get("seconds", 5), **baudrate_arg(arguments)}
+    )
+    steps.append(f"Serial output:\n{serial_output}")
+    return "\n\n".join(steps)
+
+
+def baudrate_arg(arguments):
+    return {"baudrate": arguments["baudrate"]} if "baudrate" in arguments else {}
+
+
+def schema(properties, required=()):
+    return {
+        "type": "object",
+        "properties": properties,
+        "required": list(required),
+    }
+
+
+STRING = {"type": "string"}
+SKETCH_PATH = {"type": "string", "description": "Absolute path of the sketch folder."}
+FQBN = {
+    "type": "string",
+    "description": "Fully qualified board name. Defaults to the sketch.yaml of the sketch.",
+}
+PORT = {"type": "string", "description": "Port of the board, e.g. /dev/ttyACM0 or COM3."}
+
+TOOLS = {
+    "list_boards": (
+        list_boards,
+        "Lists the boards connected to this computer with their ports and FQBNs.",
+        schema({}),
+    ),
+    "list_installed_boards": (
+        list_installed_boards,
+        "Lists all boards of the installed platforms with their FQBNs.",
+        schema({"filter": STRING}),
+    ),
+    "compile": (
+        compile_sketch,
+        "Compiles a sketch and returns the build output.",
+        schema({"sketch_path": SKETCH_PATH, "fqbn": FQBN}, ["sketch_path"]),
+    ),
+    "upload": (
+        upload_sketch,
+        "Uploads the last build of a sketch to the board on the given port.",
+        schema({"sketch_path": SKETCH_PATH, "port": PORT, "fqbn": FQBN}, ["sketch_path", "port"]),
+    ),
+    "lib_search": (
+        lib_search,
+        "Searches the Library Manager for libraries.",
+        schema({"query": STRING}, ["query"]),
+    ),
+    "lib_install": (
+        lib_install,
+        "Installs a library, optionally pinned with name@version.",
+        schema({"name": STRING}, ["name"]),
+    ),
+    "core_list": (
+        core_list,
+        "Lists the installed platforms (cores).",
+        schema({}),
+    ),
+    "core_search": (
+        core_search,
+        "Searches the platform index for platforms (cores).",
+        schema({"query": STRING}, ["query"]),
+    ),
+    "core_install": (
+        core_install,
+        "Installs a platform (core), e.g. esp32:esp32.",
+        schema({"platform": STRING}, ["platform"]),
+    ),
+    "monitor_snapshot": (
+        monitor_snapshot,
+        "Captures the serial output of a board for a few seconds (at most 60).",
+        schema(
+            {"port": PORT, "baudrate": {"type": "integer"}, "seconds": {"type": "number"}},
+            ["port"],
+        ),
+    ),
+    "flash": (
+        flash,
+        "Runs a full development iteration: detects the board (unless a port is given), "
+        "compiles the sketch, uploads it and returns the first seconds of serial output.",
+        schema(
+            {
+                "sketch_path": SKETCH_PATH,
+                "port": PORT,
+                "fqbn": FQBN,
+                "baudrate": {"type": "integer"},
+                "seconds": {"type": "number"},
+            },
+            ["sketch_path"],
+        ),
+    ),
+    "serial_start": (
+        serial_start,
+        "Starts monitoring the serial output of a board in the background, keeping "
+        f"its last {SERIAL_BUFFER_LINES} lines.",
+        schema({"port": PORT, "baudrate": {"type": "integer"}}, ["port"]),
+    ),
+    "serial_read": (
+        serial_read,
+        "Returns the most recent lines of serial output of a monitored board.",
+        schema({"port": PORT, "lines": {"type": "integer"}}, ["port"]),
+    ),
+    "serial_send": (
+        serial_send,
+        "Sends a line of text to a monitored board, e.g. a command for its serial console.",
+        schema({"port": PORT, "text": STRING}, ["port", "text"]),
+    ),
+    "serial_stop": (
+        serial_stop,
+        "Stops monitoring a board, releasing its port for uploads.",
+        schema({"port": PORT}, ["port"]),
+    ),
+}
+
+SERIAL_URI_PREFIX = "serial://"
+BUILD_LOG_URI = "build://latest/log"
+BUILD_DIAGNOSTICS_URI = "build://latest/diagnostics"
+
+
+def list_resources():
+    """Returns the resources and their MIME types."""
+    resources = [
+        {
+            "uri": SERIAL_URI_PREFIX + port,
+            "name": f"Serial output of {port}",
+            "mimeType": "text/plain",
+        }
+        for port in MONITORS
+    ]
+    if LATEST_BUILD:
+        resources += [
+            {
+                "uri": BUILD_LOG_URI,
+                "name": "Log of the latest compile",
+                "mimeType": "text/plain",
+            },
+            {
+                "uri": BUILD_DIAGNOSTICS_URI,
+                "name": "Errors and warnings of the latest compile",
+                "mimeType": "application/json",
+            },
+        ]
+    return resources
+
+
+def read_resource(uri):
+    """Returns the MIME type and text of a resource."""
+    if uri.startswith(SERIAL_URI_PREFIX):
+        monitor = MONITORS.get(uri[len(SERIAL_URI_PREFIX) :])
+        if monitor is not None:
+            return "text/plain", monitor.recent(SERIAL_BUFFER_LINES)
+    if uri in (BUILD_LOG_URI, BUILD_DIAGNOSTICS_URI):
+        if not LATEST_BUILD:
+            return "text/plain", "No sketch was compiled yet."
+        if uri == BUILD_LOG_URI:
+            return "text/plain", LATEST_BUILD["log"]
+        summary = {key: value for key, value in LATEST_BUILD.items() if key != "log"}
+        return "application/json", json.dumps(summary, indent=2)
+    raise ResourceNotFound(f"unknown resource: {uri}")
+
+
+def handle(method, params):
+    """Handles a request and returns its result."""
+    if method == "initialize":
+        return {
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": {"tools": {}, "resources": {}},
+            "serverInfo": {"name": "arduino-cli", "version": "0.0.1"},
+        }
+    if method == "ping":
+        return {}
+    if method == "tools/list":
+        return {
+            "tools": [
+                {"name": name, "description": description, "inputSchema": input_schema}
+                for name, (_, description, input_schema) in TOOLS.items()
+            ]
+        }
+    if method == "tools/call":
+        tool = TOOLS.get(params.get("name"))
+        if tool is None:
+            raise InvalidParams(f"unknown tool: {params.get('name')}")
+        try:
+            text = tool[0](params.get("arguments") or {})
+            is_error = False
+        except (ToolError, KeyError, ValueError) as error:
+            text = f"missing argument: {error}" if isinstance(error, KeyError) else str(error)
+            is_error = True
+        return {"content": [{"type": "text", "text": text}], "isError": is_error}
+    if method == "resources/list":
+        return {"resources": list_resources()}
+    if method == "resources/read":
+        uri = params.get("uri", "")
+        mime_type, text = read_resource(uri)
+        return {"contents": [{"uri": uri, "mimeType": mime_type, "text": text}]}
+    raise MethodNotFound(f"method not found: {method}")
+
+
+def main():
+    for line in sys.stdin:
+        if not line.strip():
+            continue
+        try:
+            message = json.loads(line)
+        except json.JSONDecodeError:
+            continue
+        # Notifications have no ID and get no response
+        if "id" not in message:
+            continue
+
+        response = {"jsonrpc": "2.0", "id": message["id"]}
+        try:
+            response["result"] = handle(message.get("method"), message.get("params") or {})
+        except RequestError as error:
+            response["error"] = {"code": error.code, "message": str(error)}
+        except Exception as error:
+            response["error"] = {"code": -32603, "message": str(error)}
+
+        sys.stdout.write(json.dumps(response) + "\n")
+        sys.stdout.flush()
+
+    for monitor in MONITORS.values():
+        monitor.stop()
+
+
+if __name__ == "__main__":
+    main()
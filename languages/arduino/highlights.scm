@@ -148,6 +148,15 @@ type :(primitive_type) @type.primitive
   (preproc_directive)
 ] @keyword
 
+(preproc_defined "defined" @keyword)
+
+(gnu_asm_expression
+  [
+    "asm"
+    "__asm__"
+  ] @keyword)
+(gnu_asm_qualifier) @keyword
+
 (comment) @comment
 
 [
@@ -231,3 +240,30 @@ type :(primitive_type) @type.primitive
 
 (conditional_expression ":" @operator)
 (user_defined_literal (literal_suffix) @operator)
+
+; Keywords of the Arduino core's keywords.txt, colored like in the Arduino IDE
+
+((type_identifier) @type.primitive
+ (#any-of? @type.primitive "boolean" "byte" "word"))
+
+((identifier) @type
+ (#any-of? @type
+  "Serial" "Serial1" "Serial2" "Serial3" "SerialUSB" "Keyboard" "Mouse" "Wire" "SPI"))
+
+((identifier) @function.builtin
+ (#any-of? @function.builtin
+  "pinMode" "digitalWrite" "digitalRead" "analogRead" "analogWrite" "analogReference"
+  "analogReadResolution" "analogWriteResolution" "tone" "noTone" "pulseIn" "pulseInLong"
+  "shiftIn" "shiftOut" "millis" "micros" "delay" "delayMicroseconds" "attachInterrupt"
+  "detachInterrupt" "digitalPinToInterrupt" "interrupts" "noInterrupts" "random" "randomSeed"
+  "map" "constrain" "min" "max" "abs" "sq" "sqrt" "pow" "sin" "cos" "tan" "bit" "bitClear"
+  "bitRead" "bitSet" "bitWrite" "highByte" "lowByte" "isAlpha" "isAlphaNumeric" "isAscii"
+  "isControl" "isDigit" "isGraph" "isHexadecimalDigit" "isLowerCase" "isPrintable" "isPunct"
+  "isSpace" "isUpperCase" "isWhitespace" "yield" "F"))
+
+((identifier) @constant.builtin
+ (#any-of? @constant.builtin
+  "HIGH" "LOW" "INPUT" "OUTPUT" "INPUT_PULLUP" "INPUT_PULLDOWN" "LED_BUILTIN" "LSBFIRST"
+  "MSBFIRST" "CHANGE" "FALLING" "RISING" "DEFAULT" "EXTERNAL" "INTERNAL" "INTERNAL1V1"
+  "INTERNAL2V56" "PI" "HALF_PI" "TWO_PI" "DEG_TO_RAD" "RAD_TO_DEG" "EULER" "DEC" "HEX" "OCT"
+  "BIN"))
@@ -1,7 +1,21 @@
 [
     (field_expression)
     (assignment_expression)
+    (init_declarator)
+    (if_statement)
+    (for_statement)
+    (for_range_loop)
+    (while_statement)
+    (do_statement)
+    (else_clause)
 ] @indent
 
 (_ "{" "}" @end) @indent
 (_ "(" ")" @end) @indent
+
+; The statements of a case are indented below its label, which is itself
+; indented inside the switch, like the Arduino IDE formats them
+(case_statement) @indent
+
+((comment) @indent
+    (#match? @indent "^/\\*"))
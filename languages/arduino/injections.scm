@@ -1,11 +1,23 @@
 (preproc_def
     value: (preproc_arg) @injection.content
-    (#set! injection.language "c++"))
+    (#set! injection.language "arduino"))
 
 (preproc_function_def
     value: (preproc_arg) @injection.content
-    (#set! injection.language "c++"))
+    (#set! injection.language "arduino"))
 
 (raw_string_literal
   delimiter: (raw_string_delimiter) @injection.language
   (raw_string_content) @injection.content)
+
+; The assembly of `asm volatile("...")`, highlighted by an assembly language
+; extension when one is installed
+(gnu_asm_expression
+    assembly_code: [
+        (string_literal
+            (string_content) @injection.content)
+        (concatenated_string
+            (string_literal
+                (string_content) @injection.content))
+    ]
+    (#set! injection.language "asm"))
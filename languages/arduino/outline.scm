@@ -39,6 +39,24 @@
     declarator: (init_declarator
       declarator: (_) @name)) @item
 
+; The global variables of the sketch, like pins and the objects of libraries
+(translation_unit
+    (declaration
+        .
+        (type_qualifier)? @context
+        .
+        type: (_) @context
+        declarator: [
+            (identifier) @name
+            (init_declarator
+                declarator: [
+                    (identifier) @name
+                    (pointer_declarator
+                        "*" @context
+                        declarator: (identifier) @name)
+                ])
+        ]) @item)
+
 (function_definition
     (type_qualifier)? @context
     type: (_)? @context
@@ -72,7 +90,18 @@
                     "(" @context
                     ")" @context)))
     ]
-    (type_qualifier)? @context) @item
+    (type_qualifier)? @context
+    (#not-any-of? @name "ISR" "SIGNAL")) @item
+
+; Interrupt handlers, named by their vector like `ISR TIMER1_COMPA_vect`
+(function_definition
+    declarator: (function_declarator
+        declarator: (identifier) @context
+        parameters: (parameter_list
+            .
+            (parameter_declaration
+                type: (_) @name)))
+    (#any-of? @context "ISR" "SIGNAL")) @item
 
 (declaration
     (type_qualifier)? @context
@@ -152,4 +181,16 @@
     ]
     (type_qualifier)? @context) @item
 
+(preproc_if
+    "#if" @context
+    condition: (_) @name) @item
+
+(preproc_elif
+    "#elif" @context
+    condition: (_) @name) @item
+
+(preproc_ifdef
+    ["#ifdef" "#ifndef"] @context
+    name: (_) @name) @item
+
 (comment) @annotation
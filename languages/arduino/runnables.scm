@@ -0,0 +1,17 @@
+; Offer verifying and uploading the sketch from the gutter of setup() and loop(),
+; like the buttons of the Arduino IDE
+(
+    (function_definition
+        declarator: (function_declarator
+            declarator: (identifier) @run @_name))
+    (#any-of? @_name "setup" "loop")
+    (#set! tag arduino-verify)
+)
+
+(
+    (function_definition
+        declarator: (function_declarator
+            declarator: (identifier) @run @_name))
+    (#any-of? @_name "setup" "loop")
+    (#set! tag arduino-upload)
+)
@@ -7,6 +7,13 @@
         (_)* @function.inside
         "}" )) @function.around
 
+; Callbacks like the ones passed to `attachInterrupt()`
+(lambda_expression
+    body: (_
+        "{"
+        (_)* @function.inside
+        "}")) @function.around
+
 (preproc_function_def
     value: (_) @function.inside) @function.around
 
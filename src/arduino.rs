@@ -1,53 +1,150 @@
-use std::{collections::HashMap, fs};
-use zed_extension_api::{self as zed, serde_json, settings::LspSettings, LanguageServerId, Result};
+mod cli;
+mod context_server;
+mod debugger;
+mod host;
+mod jlink;
+mod keywords;
+mod labels;
+mod libraries;
+mod openocd;
+mod pinouts;
+mod platformio;
+mod probe_rs;
+mod probes;
+mod reference;
+mod settings;
+mod sketch;
+mod slash_commands;
+mod toolchain;
+
+use serde::{Deserialize, Serialize};
+use settings::{CliConfig, ResolvedSettings};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use zed_extension_api::{
+    self as zed, serde_json, LanguageServerId, Result, SlashCommand,
+    SlashCommandArgumentCompletion, SlashCommandOutput,
+};
+
+// Directory inside the extension's working directory that receives the
+// language server's log files when logging is enabled.
+const LOG_DIR: &str = "logs";
+
+// File inside the extension's working directory recording the installed
+// language server, so new extension instances find it without asking GitHub.
+const INSTALL_STATE: &str = "language-server.json";
+
+// File held while the language server is installed, so Zed processes sharing
+// the working directory don't extract the same release over each other.
+const INSTALL_LOCK: &str = "language-server.lock";
+
+// Age after which an install lock that wasn't refreshed is considered left
+// behind by a crashed install, in seconds.
+const STALE_LOCK_AGE: u64 = 10 * 60;
+
+// How long to wait for another Zed process to finish installing the language
+// server, in seconds. The extension can't serve other requests meanwhile.
+const INSTALL_LOCK_WAIT: u64 = 30;
+
+// How long an installed language server is used before checking for a newer
+// release, in seconds.
+const UPDATE_CHECK_INTERVAL: u64 = 24 * 60 * 60;
+
+// Directory inside the extension's working directory holding the generated
+// clang-format configuration for the `format.style` setting.
+const FORMAT_DIR: &str = "format";
 
 struct ArduinoExtension {
     cached_binary_path: Option<String>,
+    // Command-line-level settings each worktree's language server was last
+    // started with, used to tell when a settings change needs a restart
+    command_line_settings: HashMap<u64, serde_json::Value>,
+    // Command each worktree's language server was last started with, shown by
+    // /arduino-config
+    launched_commands: HashMap<u64, zed::Command>,
+    // Pin constants of the board the language server was last started for,
+    // shown in completion labels
+    pin_constants: Vec<(String, String)>,
+    // Whether completion labels hide reserved identifiers, from the settings the
+    // language server was last started with
+    hide_internal_completions: bool,
 }
 
 impl ArduinoExtension {
+    /// Returns the path of the language server, installing it if needed.
+    ///
+    /// Zed processes sharing the working directory install it one at a time
+    /// through [`InstallLock`], so the ones waiting find it installed.
     fn language_server_binary_path(
         &mut self,
         language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
+        settings: &ResolvedSettings,
     ) -> Result<String> {
         // Check for explicit path override in settings
-        if let Ok(lsp_settings) = LspSettings::for_worktree("arduino", worktree) {
-            if let Some(binary) = lsp_settings.binary {
-                if let Some(path) = binary.path {
-                    // Note: If a custom path is provided, we assume it's correct
-                    // and don't perform our download/versioning logic.
-                    return Ok(path.clone());
-                }
-            }
+        if let Some(path) = settings
+            .binary
+            .as_ref()
+            .and_then(|binary| binary.path.clone())
+        {
+            // Note: If a custom path is provided, we assume it's correct
+            // and don't perform our download/versioning logic.
+            return Ok(path);
         }
 
         // Check if the binary is already available in the system's PATH
-        if let Some(path) = worktree.which("arduino_language_server") {
+        if let Some(path) = host::which(worktree, "arduino_language_server") {
             return Ok(path);
         }
 
         // Check if we've cached a binary path from a previous download
         // and that it still exists
         if let Some(path) = &self.cached_binary_path {
-            if fs::metadata(path).map_or(false, |stat| stat.is_file()) {
+            if fs::metadata(path).is_ok_and(|stat| stat.is_file()) {
                 return Ok(path.clone());
             }
         }
 
+        // Use the version installed by a previous session until it's time to
+        // check for updates again
+        let installed = InstallState::read();
+        if let Some(installed) = &installed {
+            if now().saturating_sub(installed.checked_at) < UPDATE_CHECK_INTERVAL {
+                self.cached_binary_path = Some(installed.path.clone());
+                return Ok(installed.path.clone());
+            }
+        }
+
         // If none of the above, proceed with downloading the latest version
         zed::set_language_server_installation_status(
             language_server_id,
             &zed::LanguageServerInstallationStatus::CheckingForUpdate,
         );
 
-        let release = zed::latest_github_release(
+        let release = match zed::latest_github_release(
             "arduino/arduino-language-server",
             zed::GithubReleaseOptions {
                 require_assets: true,
                 pre_release: false,
             },
-        )?;
+        ) {
+            Ok(release) => release,
+            // Keep working offline with the installed version
+            Err(e) => match installed {
+                Some(installed) => {
+                    eprintln!("arduino: failed to check for language server updates: {e}");
+                    self.cached_binary_path = Some(installed.path.clone());
+                    return Ok(installed.path);
+                }
+                None => return Err(e),
+            },
+        };
 
         let (platform, arch) = zed::current_platform();
 
@@ -86,13 +183,16 @@ impl ArduinoExtension {
         // Construct the full path to the binary *inside* the versioned directory
         let final_binary_path = format!("{}/{}", version_dir, binary_name);
 
-        // Check if the binary already exists at the expected versioned path
-        if !fs::metadata(&final_binary_path).map_or(false, |stat| stat.is_file()) {
+        // Check if the binary already exists at the expected versioned path, once no other
+        // process is extracting it
+        let lock = InstallLock::acquire()?;
+        if !fs::metadata(&final_binary_path).is_ok_and(|stat| stat.is_file()) {
             zed::set_language_server_installation_status(
                 language_server_id,
                 &zed::LanguageServerInstallationStatus::Downloading,
             );
 
+            lock.refresh();
             // Download the archive. The target path for download_file is the directory
             // where the archive should be extracted.
             zed::download_file(
@@ -101,9 +201,10 @@ impl ArduinoExtension {
                 zed::DownloadedFileType::GzipTar,
             )
             .map_err(|e| format!("failed to download file: {e}"))?;
+            lock.refresh();
 
-            // Clean up old versions: Remove any directories in the current download location
-            // that are not the newly downloaded version directory.
+            // Clean up old versions: Remove any language server directories in the current
+            // download location that are not the newly downloaded version directory.
             let entries =
                 fs::read_dir(".").map_err(|e| format!("failed to list working directory {e}"))?;
             for entry in entries {
@@ -112,11 +213,17 @@ impl ArduinoExtension {
                     format!("failed to get file type for {:?}: {}", entry.path(), e)
                 })?;
 
-                if file_type.is_dir() {
-                    if entry.file_name().to_str() != Some(&version_dir) {
-                        // Ignore errors during cleanup as they aren't critical
-                        fs::remove_dir_all(entry.path()).ok();
-                    }
+                let file_name = entry.file_name();
+                let Some(file_name) = file_name.to_str() else {
+                    continue;
+                };
+
+                if file_type.is_dir()
+                    && file_name.starts_with("arduino-language-server-")
+                    && file_name != version_dir
+                {
+                    // Ignore errors during cleanup as they aren't critical
+                    fs::remove_dir_all(entry.path()).ok();
                 }
             }
 
@@ -124,15 +231,191 @@ impl ArduinoExtension {
             zed::make_file_executable(&final_binary_path)?;
         }
 
+        InstallState {
+            version: release.version,
+            path: final_binary_path.clone(),
+            checked_at: now(),
+        }
+        .write();
         self.cached_binary_path = Some(final_binary_path.clone());
         Ok(final_binary_path)
     }
 }
 
+/// The language server installed in the extension's working directory, and
+/// when its release was last checked against GitHub.
+#[derive(Serialize, Deserialize)]
+struct InstallState {
+    version: String,
+    path: String,
+    checked_at: u64,
+}
+
+impl InstallState {
+    /// Reads the recorded installation, if its binary still exists.
+    fn read() -> Option<Self> {
+        let state: Self = serde_json::from_str(&fs::read_to_string(INSTALL_STATE).ok()?).ok()?;
+        fs::metadata(&state.path)
+            .is_ok_and(|stat| stat.is_file())
+            .then_some(state)
+    }
+
+    fn write(&self) {
+        // Failing to record it only costs a check for updates next time
+        if let Ok(state) = serde_json::to_string(self) {
+            fs::write(INSTALL_STATE, state).ok();
+        }
+    }
+}
+
+/// Lock on installing the language server, held by one Zed process at a time
+/// and released when dropped.
+struct InstallLock {
+    // Contents of the lock file while this process holds it, to tell it apart
+    // from a lock another process took over after considering this one stale
+    token: String,
+}
+
+impl InstallLock {
+    /// Takes the lock, waiting up to [`INSTALL_LOCK_WAIT`] for another process
+    /// to finish its install first. Locks left behind by a crashed install are
+    /// taken over.
+    fn acquire() -> Result<Self> {
+        let token = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos())
+            .to_string();
+        let deadline = now() + INSTALL_LOCK_WAIT;
+        loop {
+            let created = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(INSTALL_LOCK);
+            match created {
+                Ok(mut file) => {
+                    file.write_all(token.as_bytes()).ok();
+                    return Ok(Self { token });
+                }
+                // Install without the lock rather than not at all. It is never
+                // held, so dropping it leaves other processes' locks alone
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+                    eprintln!("arduino: failed to create {INSTALL_LOCK}: {e}");
+                    return Ok(Self { token });
+                }
+                Err(_) => {}
+            }
+
+            let stale = match fs::metadata(INSTALL_LOCK) {
+                Ok(stat) => stat
+                    .modified()
+                    .ok()
+                    .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+                    .is_none_or(|age| age.as_secs() > STALE_LOCK_AGE),
+                // The lock was just released
+                Err(_) => continue,
+            };
+            if stale {
+                fs::remove_file(INSTALL_LOCK).ok();
+            } else if now() >= deadline {
+                return Err(
+                    "another Zed process is installing the arduino language server, restart the language server once it finished"
+                        .to_string(),
+                );
+            } else {
+                thread::sleep(Duration::from_millis(500));
+            }
+        }
+    }
+
+    /// Returns whether the lock file is still the one this process created.
+    fn is_held(&self) -> bool {
+        fs::read_to_string(INSTALL_LOCK).is_ok_and(|contents| contents == self.token)
+    }
+
+    /// Updates the lock's modification time, so other processes don't consider
+    /// it stale during a long install.
+    fn refresh(&self) {
+        if self.is_held() {
+            fs::write(INSTALL_LOCK, &self.token).ok();
+        }
+    }
+}
+
+impl Drop for InstallLock {
+    fn drop(&mut self) {
+        if self.is_held() {
+            fs::remove_file(INSTALL_LOCK).ok();
+        }
+    }
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Looks up an environment variable in the language server's environment
+/// first, then in the worktree's shell environment and finally in the
+/// extension's own environment.
+fn env_var(env: &HashMap<String, String>, worktree: &zed::Worktree, name: &str) -> Option<String> {
+    env.get(name)
+        .cloned()
+        .or_else(|| {
+            host::shell_env(worktree)
+                .into_iter()
+                .find_map(|(key, value)| (key == name).then_some(value))
+        })
+        .or_else(|| std::env::var(name).ok())
+        .filter(|value| !value.is_empty())
+}
+
+/// Returns the Arduino data directory (`Arduino15`), honoring an
+/// `ARDUINO_DIRECTORIES_DATA` override and the platform's default otherwise.
+/// Returns `None` when the variables it is derived from aren't set.
+fn arduino_data_dir(env: &HashMap<String, String>, worktree: &zed::Worktree) -> Option<PathBuf> {
+    let var = |name: &str| env_var(env, worktree, name);
+    if let Some(data_dir) = var("ARDUINO_DIRECTORIES_DATA") {
+        return Some(PathBuf::from(data_dir));
+    }
+
+    match zed::current_platform().0 {
+        zed::Os::Mac => var("HOME").map(|home| PathBuf::from(home).join("Library/Arduino15")),
+        zed::Os::Linux => var("HOME").map(|home| PathBuf::from(home).join(".arduino15")),
+        zed::Os::Windows => var("LOCALAPPDATA")
+            .map(|local_app_data| PathBuf::from(local_app_data).join("Arduino15")),
+    }
+}
+
+/// Returns the sketchbook directory, honoring an `ARDUINO_DIRECTORIES_USER`
+/// override and the platform's default otherwise.
+fn arduino_sketchbook_dir(
+    env: &HashMap<String, String>,
+    worktree: &zed::Worktree,
+) -> Option<PathBuf> {
+    let var = |name: &str| env_var(env, worktree, name);
+    if let Some(sketchbook_dir) = var("ARDUINO_DIRECTORIES_USER") {
+        return Some(PathBuf::from(sketchbook_dir));
+    }
+
+    match zed::current_platform().0 {
+        zed::Os::Mac => var("HOME").map(|home| PathBuf::from(home).join("Documents/Arduino")),
+        zed::Os::Linux => var("HOME").map(|home| PathBuf::from(home).join("Arduino")),
+        zed::Os::Windows => {
+            var("USERPROFILE").map(|profile| PathBuf::from(profile).join("Documents\\Arduino"))
+        }
+    }
+}
+
 impl zed::Extension for ArduinoExtension {
     fn new() -> Self {
         Self {
             cached_binary_path: None,
+            command_line_settings: HashMap::new(),
+            launched_commands: HashMap::new(),
+            pin_constants: Vec::new(),
+            hide_internal_completions: true,
         }
     }
 
@@ -141,94 +424,212 @@ impl zed::Extension for ArduinoExtension {
         language_server_id: &LanguageServerId,
         worktree: &zed::Worktree,
     ) -> Result<zed::Command> {
-        // Get args and env from LSP settings first
-        let mut args: Vec<String> = Vec::new();
-        let mut env: HashMap<String, String> = HashMap::new();
-
-        if let Ok(lsp_settings) = LspSettings::for_worktree("arduino", worktree) {
-            if let Some(binary) = lsp_settings.binary {
-                if let Some(binary_args) = binary.arguments {
-                    args = binary_args;
-                }
+        // Read the settings once for everything below. Refuse to start with malformed
+        // settings, so the error shows up in Zed instead of the setting being silently ignored
+        let settings = ResolvedSettings::for_worktree(worktree)
+            .map_err(|e| format!("invalid arduino LSP settings: {e}"))?;
+        self.command_line_settings
+            .insert(worktree.id(), settings.command_line_fingerprint());
 
-                if let Some(binary_env) = binary.env {
-                    env = binary_env;
-                }
-            }
-        }
+        // Look up the tools again on every start, as the ones found before may have been
+        // removed or moved
+        host::forget_tools(worktree);
 
         // Get the path to the language server binary
-        let command_path = self.language_server_binary_path(language_server_id, worktree)?;
+        let command_path =
+            self.language_server_binary_path(language_server_id, worktree, &settings)?;
+
+        // Get args from LSP settings first
+        let mut args = settings
+            .binary
+            .as_ref()
+            .and_then(|binary| binary.arguments.clone())
+            .unwrap_or_default();
 
         // Check if the user already specified the -clangd flag in settings
         let user_specified_clangd = args.iter().any(|arg| arg == "-clangd");
         let user_specified_cli = args.iter().any(|arg| arg == "-cli");
         let user_specified_cli_config = args.iter().any(|arg| arg == "-cli-config");
+        let user_specified_cli_daemon = args
+            .iter()
+            .any(|arg| arg == "-cli-daemon-addr" || arg == "-cli-daemon-instance");
+        let user_specified_fqbn = args.iter().any(|arg| arg == "-fqbn");
+        let user_specified_log = args.iter().any(|arg| arg == "-log");
+        let user_specified_format_conf = args.iter().any(|arg| arg == "-format-conf-path");
+        // Boolean flags may also be given as `-flag=false`
+        let user_specified_no_real_time_diagnostics = args
+            .iter()
+            .any(|arg| arg.starts_with("-no-real-time-diagnostics"));
+        let user_specified_skip_libraries_discovery = args
+            .iter()
+            .any(|arg| arg.starts_with("-skip-libraries-discovery-on-rebuild"));
 
-        if !user_specified_cli_config {
-            // Set the default cli-config path based on OS
-            let cli_config_path = match zed::current_platform().0 {
-                zed::Os::Mac => {
-                    let home = std::env::home_dir().expect("Failed to get home directory");
-                    home.join("Library/Arduino15/arduino-cli.yaml")
-                }
-                zed::Os::Linux => {
-                    let home = std::env::home_dir().expect("Failed to get home directory");
-                    home.join(".arduino15/arduino-cli.yaml")
+        // Determine environment variables.
+        // Start from the worktree's shell environment on Mac/Linux, which includes whatever
+        // direnv, mise or asdf set up for the project, so their tool shims keep working.
+        // It is fetched again on every start, so restarting the language server picks up
+        // changes to it, and reused by everything else until then.
+        // Environment variables provided in settings are applied on top of it, and the
+        // configured directories are exported so every arduino-cli invocation of the
+        // language server uses them, unless the variables were set explicitly
+        let mut env = settings.env(match zed::current_platform().0 {
+            zed::Os::Mac | zed::Os::Linux => host::refresh_shell_env(worktree),
+            zed::Os::Windows => Vec::new(), // Windows doesn't typically need shell_env
+        });
+        let arduino_settings = settings.arduino;
+
+        // When talking to an arduino-cli daemon, the language server neither spawns
+        // arduino-cli nor reads its configuration file
+        let use_cli_daemon =
+            user_specified_cli_daemon || arduino_settings.cli.daemon_address.is_some();
+
+        if !user_specified_cli_daemon {
+            if let (Some(address), Some(instance)) = (
+                &arduino_settings.cli.daemon_address,
+                arduino_settings.cli.daemon_instance,
+            ) {
+                args.push("-cli-daemon-addr".to_string());
+                args.push(address.clone());
+                args.push("-cli-daemon-instance".to_string());
+                args.push(instance.to_string());
+            }
+        }
+
+        if !user_specified_cli_config && !use_cli_daemon {
+            match &arduino_settings.cli_config {
+                CliConfig::Path(cli_config) => {
+                    args.push("-cli-config".to_string());
+                    args.push(cli_config.clone());
                 }
-                zed::Os::Windows => {
-                    let local_app_data =
-                        std::env::var("LOCALAPPDATA").expect("LOCALAPPDATA not found");
-
-                    let mut path = std::path::PathBuf::from(&local_app_data);
-                    path.push("Arduino15");
-                    path.push("arduino-cli.yaml");
-                    path
+                CliConfig::Disabled => {}
+                CliConfig::Auto => {
+                    if let Some(data_dir) = arduino_data_dir(&env, worktree) {
+                        // Use the default cli-config path inside the Arduino data directory.
+                        // The extension can't see the host file system, and checking for the
+                        // file would delay the start, so it is passed even if it doesn't exist,
+                        // in which case arduino-cli falls back to its defaults
+                        let cli_config_path = data_dir.join("arduino-cli.yaml");
+                        args.push("-cli-config".to_string());
+                        args.push(cli_config_path.to_string_lossy().to_string());
+                    } else {
+                        eprintln!(
+                            "arduino: could not determine the Arduino data directory, not passing -cli-config"
+                        );
+                    }
                 }
-            };
-            if cli_config_path.exists() {
-                args.push("-cli-config".to_string());
-                args.push(cli_config_path.to_string_lossy().to_string());
             }
         }
 
         if !user_specified_clangd {
-            // User did not specify -clangd, try to find it automatically
-            if let Some(clangd_path) = worktree.which("clangd") {
+            // User did not specify -clangd, use the configured path or try to find it automatically
+            let clangd_path = arduino_settings.clangd_path.clone().or_else(|| {
+                arduino_settings
+                    .auto_detect_tools
+                    .then(|| host::which(worktree, "clangd"))
+                    .flatten()
+            });
+            if let Some(clangd_path) = clangd_path {
                 // Add the flag and its value to the arguments
                 args.push("-clangd".to_string());
                 args.push(clangd_path);
             }
         }
 
-        if !user_specified_cli {
-            if let Some(cli_path) = worktree.which("arduino-cli") {
+        if !user_specified_cli && !use_cli_daemon {
+            let cli_path = arduino_settings.cli_path.clone().or_else(|| {
+                arduino_settings
+                    .auto_detect_tools
+                    .then(|| host::which(worktree, "arduino-cli"))
+                    .flatten()
+            });
+            if let Some(cli_path) = cli_path {
                 args.push("-cli".to_string());
                 args.push(cli_path);
             }
         }
 
-        // Determine environment variables.
-        // If environment variables were provided in settings, use those.
-        // Otherwise, use shell_env on Mac/Linux as a default.
-        if env.is_empty() {
-            // Only apply default if no env was set in settings
-            let default_env = match zed::current_platform().0 {
-                zed::Os::Mac | zed::Os::Linux => worktree.shell_env(),
-                zed::Os::Windows => Vec::new(), // Windows doesn't typically need shell_env
-            };
+        let active_fqbn = sketch::active_fqbn(worktree, &arduino_settings);
+        self.hide_internal_completions = arduino_settings.lsp.hide_internal_completions;
+        self.pin_constants = active_fqbn
+            .as_deref()
+            .map(pinouts::pin_constants)
+            .unwrap_or_default();
+        if !user_specified_fqbn {
+            // Fall back to the board attached to the sketch, e.g. with /arduino-fqbn
+            if let Some(fqbn) = active_fqbn {
+                args.push("-fqbn".to_string());
+                args.push(fqbn);
+            }
+        }
+
+        if !user_specified_log {
+            // Translate the verbosity setting into the language server's logging flags,
+            // writing logs into the extension's working directory
+            let verbosity = arduino_settings.lsp.verbosity;
+            if verbosity != settings::Verbosity::Off {
+                fs::create_dir_all(LOG_DIR)
+                    .map_err(|e| format!("failed to create log directory: {e}"))?;
+                let log_dir = std::env::current_dir()
+                    .map_err(|e| format!("failed to get working directory: {e}"))?
+                    .join(LOG_DIR);
+                args.extend(verbosity.language_server_args(&log_dir.to_string_lossy()));
+            }
+        }
 
-            // Convert default_env (Vec<(String, String)>) to HashMap
-            for (key, value) in default_env {
-                env.insert(key, value);
+        if !user_specified_format_conf {
+            // The language server prefers the sketch's .clang-format and falls back to its
+            // built-in Arduino style, so a global configuration is only written for the others
+            if let Some(config) = arduino_settings.format.style.clang_format_config() {
+                fs::create_dir_all(FORMAT_DIR)
+                    .map_err(|e| format!("failed to create format directory: {e}"))?;
+                let config_path = std::env::current_dir()
+                    .map_err(|e| format!("failed to get working directory: {e}"))?
+                    .join(FORMAT_DIR)
+                    .join(".clang-format");
+                fs::write(&config_path, config)
+                    .map_err(|e| format!("failed to write clang-format configuration: {e}"))?;
+                args.push("-format-conf-path".to_string());
+                args.push(config_path.to_string_lossy().to_string());
             }
         }
 
-        Ok(zed::Command {
+        if !user_specified_no_real_time_diagnostics && !arduino_settings.real_time_diagnostics {
+            args.push("-no-real-time-diagnostics".to_string());
+        }
+
+        if !user_specified_skip_libraries_discovery
+            && arduino_settings.skip_library_discovery_on_rebuild
+        {
+            args.push("-skip-libraries-discovery-on-rebuild".to_string());
+        }
+
+        // Let clangd run the compilers of the installed cores, including the ones
+        // installed by hand in the sketchbook, to find their system headers
+        let query_drivers: Vec<String> = [
+            arduino_data_dir(&env, worktree).map(|dir| dir.join("packages")),
+            arduino_sketchbook_dir(&env, worktree).map(|dir| dir.join("hardware")),
+        ]
+        .into_iter()
+        .flatten()
+        // clangd reads backslashes in CLANGD_FLAGS as escapes
+        .map(|dir| format!("{}/**", dir.to_string_lossy().replace('\\', "/")))
+        .collect();
+
+        // Forward the clangd flags unless the user already set their own
+        env.entry("CLANGD_FLAGS".to_string())
+            .or_insert_with(|| arduino_settings.lsp.clangd_flags(&query_drivers));
+
+        // Append the user's extra arguments last, so they win over the flags computed above
+        args.extend(arduino_settings.extra_args);
+
+        let command = zed::Command {
             command: command_path,
             args,
             env: env.into_iter().collect(),
-        })
+        };
+        self.launched_commands
+            .insert(worktree.id(), command.clone());
+        Ok(command)
     }
 
     fn language_server_workspace_configuration(
@@ -236,14 +637,160 @@ impl zed::Extension for ArduinoExtension {
         _language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
     ) -> Result<Option<serde_json::Value>> {
-        // This function provides the `workspace/configuration` response to the language server
-        // Get the 'settings' section from the arduino LSP settings in Zed
-        let settings = LspSettings::for_worktree("arduino", worktree)
-            .ok()
-            .and_then(|lsp_settings| lsp_settings.settings.clone())
-            .unwrap_or_default();
+        // This function provides the `workspace/configuration` response to the language server.
+        // Zed pushes it again through `workspace/didChangeConfiguration` whenever the settings
+        // change, so only the settings the running server can pick up are forwarded. Settings
+        // the extension turns into command-line flags need a restart instead.
+        // Malformed settings keep the language server from starting, which reports them
+        let Ok(settings) = ResolvedSettings::for_worktree(worktree) else {
+            return Ok(Some(serde_json::Value::default()));
+        };
+
+        // Extensions can't restart the language server, so only report the settings that need
+        // it, as /arduino-config does
+        if let Some(started_with) = self.command_line_settings.get(&worktree.id()) {
+            let changed = settings.changed_since(started_with);
+            if !changed.is_empty() {
+                eprintln!(
+                    "arduino: restart the language server (`editor: restart language server`) to apply the changed {}",
+                    changed.join(", ")
+                );
+            }
+        }
+
+        Ok(Some(settings.runtime))
+    }
+
+    fn label_for_completion(
+        &self,
+        _language_server_id: &LanguageServerId,
+        completion: zed::lsp::Completion,
+    ) -> Option<zed::CodeLabel> {
+        labels::completion(
+            completion,
+            &self.pin_constants,
+            self.hide_internal_completions,
+        )
+    }
+
+    fn label_for_symbol(
+        &self,
+        _language_server_id: &LanguageServerId,
+        symbol: zed::lsp::Symbol,
+    ) -> Option<zed::CodeLabel> {
+        labels::symbol(symbol)
+    }
+
+    fn complete_slash_command_argument(
+        &self,
+        command: SlashCommand,
+        args: Vec<String>,
+    ) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+        slash_commands::complete(command, args)
+    }
+
+    fn context_server_command(
+        &mut self,
+        context_server_id: &zed::ContextServerId,
+        project: &zed::Project,
+    ) -> Result<zed::Command> {
+        context_server::command(context_server_id.as_ref(), project)
+    }
+
+    fn get_dap_binary(
+        &mut self,
+        adapter_name: String,
+        config: zed::DebugTaskDefinition,
+        user_provided_debug_adapter_path: Option<String>,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::DebugAdapterBinary, String> {
+        match adapter_name.as_str() {
+            debugger::ADAPTER_NAME => {
+                debugger::binary(&config, user_provided_debug_adapter_path, worktree)
+            }
+            _ => Err(format!("unknown debug adapter: {adapter_name}")),
+        }
+    }
+
+    fn dap_request_kind(
+        &mut self,
+        adapter_name: String,
+        config: serde_json::Value,
+    ) -> Result<zed::StartDebuggingRequestArgumentsRequest, String> {
+        match adapter_name.as_str() {
+            debugger::ADAPTER_NAME => debugger::request_kind(&config),
+            _ => Err(format!("unknown debug adapter: {adapter_name}")),
+        }
+    }
+
+    fn dap_config_to_scenario(
+        &mut self,
+        config: zed::DebugConfig,
+    ) -> Result<zed::DebugScenario, String> {
+        match config.adapter.as_str() {
+            debugger::ADAPTER_NAME => debugger::scenario(config),
+            _ => Err(format!("unknown debug adapter: {}", config.adapter)),
+        }
+    }
 
-        Ok(Some(settings))
+    fn dap_locator_create_scenario(
+        &mut self,
+        locator_name: String,
+        build_task: zed::TaskTemplate,
+        resolved_label: String,
+        debug_adapter_name: String,
+    ) -> Option<zed::DebugScenario> {
+        match locator_name.as_str() {
+            debugger::LOCATOR_NAME => {
+                debugger::locator_scenario(&build_task, &resolved_label, &debug_adapter_name)
+            }
+            _ => None,
+        }
+    }
+
+    fn run_dap_locator(
+        &mut self,
+        locator_name: String,
+        build_task: zed::TaskTemplate,
+    ) -> Result<zed::DebugRequest, String> {
+        match locator_name.as_str() {
+            debugger::LOCATOR_NAME => debugger::run_locator(build_task),
+            _ => Err(format!("unknown debug locator: {locator_name}")),
+        }
+    }
+
+    fn suggest_docs_packages(&self, provider: String) -> Result<Vec<String>, String> {
+        match provider.as_str() {
+            libraries::DOCS_PROVIDER => libraries::suggest_docs_packages(),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn index_docs(
+        &self,
+        provider: String,
+        package: String,
+        database: &zed::KeyValueStore,
+    ) -> Result<(), String> {
+        match provider.as_str() {
+            libraries::DOCS_PROVIDER => libraries::index_docs(&package, database),
+            _ => Err(format!("unknown docs provider: {provider}")),
+        }
+    }
+
+    fn run_slash_command(
+        &self,
+        command: SlashCommand,
+        args: Vec<String>,
+        worktree: Option<&zed::Worktree>,
+    ) -> Result<SlashCommandOutput, String> {
+        if command.name == "arduino-config" {
+            let launched = worktree.and_then(|worktree| self.launched_commands.get(&worktree.id()));
+            let started_with =
+                worktree.and_then(|worktree| self.command_line_settings.get(&worktree.id()));
+            return slash_commands::config(worktree, launched, started_with);
+        }
+        slash_commands::run(command, args, worktree)
     }
 }
 
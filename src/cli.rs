@@ -0,0 +1,175 @@
+use crate::{host, settings::ResolvedSettings};
+use zed_extension_api::{
+    self as zed,
+    process::{Command, Output},
+    serde_json, Result,
+};
+
+/// An `arduino-cli` executable, resolved the same way as for the language
+/// server.
+pub struct ArduinoCli {
+    path: String,
+    env: Vec<(String, String)>,
+}
+
+impl ArduinoCli {
+    /// Resolves `arduino-cli` for `worktree`, falling back to the one on Zed's
+    /// own `PATH` when there is no worktree to read settings from.
+    pub fn for_worktree(worktree: Option<&zed::Worktree>) -> Result<Self> {
+        match worktree {
+            Some(worktree) => Self::new(worktree, &ResolvedSettings::for_worktree(worktree)?),
+            None => Ok(Self {
+                path: "arduino-cli".to_string(),
+                env: Vec::new(),
+            }),
+        }
+    }
+
+    /// Resolves `arduino-cli` for `worktree` from its settings, and runs it
+    /// in the same environment as the language server.
+    pub fn new(worktree: &zed::Worktree, settings: &ResolvedSettings) -> Result<Self> {
+        let arduino = &settings.arduino;
+        let path = arduino
+            .cli_path
+            .clone()
+            .or_else(|| {
+                arduino
+                    .auto_detect_tools
+                    .then(|| host::which(worktree, "arduino-cli"))
+                    .flatten()
+            })
+            .ok_or_else(|| {
+                "arduino-cli not found, install it or set `cli_path` in the arduino settings"
+                    .to_string()
+            })?;
+
+        let env = settings.env(match zed::current_platform().0 {
+            zed::Os::Mac | zed::Os::Linux => host::shell_env(worktree),
+            zed::Os::Windows => Vec::new(),
+        });
+
+        Ok(Self {
+            path,
+            env: env.into_iter().collect(),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Runs `arduino-cli` with `args`, returning its output regardless of the
+    /// exit status.
+    pub fn output(&self, args: &[&str]) -> Result<Output> {
+        Command::new(&self.path)
+            .args(args.iter().copied())
+            .envs(self.env.iter().cloned())
+            .output()
+            .map_err(|e| {
+                host::forget_tool(&self.path);
+                format!("failed to run arduino-cli: {e}")
+            })
+    }
+
+    /// Runs `arduino-cli` with `args`, returning its standard output.
+    pub fn run(&self, args: &[&str]) -> Result<String> {
+        let output = self.output(args)?;
+
+        if output.status != Some(0) {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!(
+                "`arduino-cli {}` failed: {}",
+                args.join(" "),
+                stderr.trim()
+            ));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Runs a long-running `arduino-cli` command such as `monitor` for at
+    /// most `seconds`, or until it printed `max_lines`, and returns what it
+    /// printed until then.
+    ///
+    /// Zed only returns the output of processes once they exit, so the
+    /// command is wrapped in a shell script stopping it in time.
+    pub fn capture(&self, args: &[&str], seconds: u32, max_lines: Option<usize>) -> Result<String> {
+        let seconds = seconds.to_string();
+        let command = match zed::current_platform().0 {
+            zed::Os::Mac | zed::Os::Linux => {
+                const SCRIPT: &str = r#"seconds=$1; lines=$2; shift 2
+log=$(mktemp)
+"$@" >"$log" 2>&1 &
+pid=$!
+while [ "$seconds" -gt 0 ] && kill -0 "$pid" 2>/dev/null && [ "$(wc -l <"$log")" -lt "$lines" ]; do
+  sleep 1
+  seconds=$((seconds - 1))
+done
+kill "$pid" 2>/dev/null
+wait "$pid" 2>/dev/null
+head -n "$lines" "$log"
+rm -f "$log""#;
+                let lines = max_lines.unwrap_or(usize::MAX >> 1).to_string();
+                Command::new("sh")
+                    .args(["-c", SCRIPT, "sh", &seconds, &lines, &self.path])
+                    .args(args.iter().copied())
+            }
+            zed::Os::Windows => {
+                let lines = max_lines.unwrap_or(usize::MAX >> 1);
+                let args: Vec<String> =
+                    args.iter().map(|arg| host::quote_powershell(arg)).collect();
+                Command::new("powershell").args([
+                    "-NoProfile".to_string(),
+                    "-Command".to_string(),
+                    format!(
+                        "$job = Start-Job -ScriptBlock {{ param($cli, $arguments) & $cli @arguments 2>&1 }} -ArgumentList {}, @({}); \
+                         $deadline = (Get-Date).AddSeconds({seconds}); \
+                         while ($job.State -eq 'Running' -and (Get-Date) -lt $deadline -and @(Receive-Job $job -Keep).Count -lt {lines}) {{ Start-Sleep -Seconds 1 }}; \
+                         Stop-Job $job; Receive-Job $job | Select-Object -First {lines}",
+                        host::quote_powershell(&self.path),
+                        args.join(", ")
+                    ),
+                ])
+            }
+        };
+        let output = command
+            .envs(self.env.iter().cloned())
+            .output()
+            .map_err(|e| {
+                host::forget_tool(&self.path);
+                format!("failed to run arduino-cli: {e}")
+            })?;
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let lines: Vec<&str> = stdout.lines().collect();
+        let count = max_lines.unwrap_or(lines.len()).min(lines.len());
+        Ok(lines[..count].join("\n"))
+    }
+
+    /// Returns the port of the first connected board arduino-cli recognizes.
+    pub fn detected_port(&self) -> Result<Option<String>> {
+        let detected = self.run_json(&["board", "list"])?;
+        // Newer arduino-cli versions wrap the list in a `detected_ports` object
+        let detected_ports = detected.get("detected_ports").unwrap_or(&detected);
+        Ok(detected_ports
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|detected_port| {
+                detected_port["matching_boards"]
+                    .as_array()
+                    .is_some_and(|boards| !boards.is_empty())
+            })
+            .find_map(|detected_port| detected_port["port"]["address"].as_str())
+            .map(ToString::to_string))
+    }
+
+    /// Runs `arduino-cli` with `args` and parses its JSON output.
+    pub fn run_json(&self, args: &[&str]) -> Result<serde_json::Value> {
+        let mut args = args.to_vec();
+        args.extend(["--format", "json"]);
+        let stdout = self.run(&args)?;
+        serde_json::from_str(&stdout)
+            .map_err(|e| format!("failed to parse arduino-cli output: {e}"))
+    }
+}
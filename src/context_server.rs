@@ -0,0 +1,74 @@
+use crate::settings::MonitorOptions;
+use serde::Deserialize;
+use std::{env, fs};
+use zed_extension_api::{self as zed, settings::ContextServerSettings, Command, Result};
+
+/// The MCP server wrapping arduino-cli. It is written to the extension's
+/// working directory on every start, so updates of the extension apply.
+const SERVER_SCRIPT: &str = include_str!("../context_server/arduino_cli_mcp.py");
+const SERVER_SCRIPT_PATH: &str = "arduino_cli_mcp.py";
+
+pub fn command(context_server_id: &str, project: &zed::Project) -> Result<Command> {
+    let settings = ContextServerSettings::for_project(context_server_id, project)?;
+
+    fs::write(SERVER_SCRIPT_PATH, SERVER_SCRIPT)
+        .map_err(|e| format!("failed to write context server script: {e}"))?;
+    let script = env::current_dir()
+        .map_err(|e| format!("failed to get working directory: {e}"))?
+        .join(SERVER_SCRIPT_PATH);
+
+    // `command.path` selects the Python interpreter running the server
+    let command = settings.command.as_ref();
+    let python = command
+        .and_then(|command| command.path.clone())
+        .unwrap_or_else(|| match zed::current_platform().0 {
+            zed::Os::Mac | zed::Os::Linux => "python3".to_string(),
+            zed::Os::Windows => "python".to_string(),
+        });
+
+    let mut args = vec![script.to_string_lossy().to_string()];
+    args.extend(
+        command
+            .and_then(|command| command.arguments.clone())
+            .unwrap_or_default(),
+    );
+
+    let mut env: Vec<(String, String)> = command
+        .and_then(|command| command.env.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    if let Some(cli_path) = settings
+        .settings
+        .as_ref()
+        .and_then(|settings| settings.get("cli_path"))
+        .and_then(|cli_path| cli_path.as_str())
+    {
+        env.push(("ARDUINO_CLI".to_string(), cli_path.to_string()));
+    }
+
+    // Same format as `monitor` in the arduino LSP settings
+    if let Some(monitor) = settings
+        .settings
+        .as_ref()
+        .and_then(|settings| settings.get("monitor"))
+    {
+        let monitor = MonitorOptions::deserialize(monitor).map_err(|e| {
+            format!("invalid value for `monitor` in the arduino-cli context server settings: {e}")
+        })?;
+        env.push((
+            "ARDUINO_MONITOR_CONFIG".to_string(),
+            monitor.port_config().join(","),
+        ));
+        env.push((
+            "ARDUINO_MONITOR_LINE_ENDING".to_string(),
+            monitor.line_ending.as_str().to_string(),
+        ));
+    }
+
+    Ok(Command {
+        command: python,
+        args,
+        env,
+    })
+}
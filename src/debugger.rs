@@ -0,0 +1,696 @@
+use crate::{
+    cli::ArduinoCli, host, jlink, openocd, probe_rs, probes, settings::ResolvedSettings, sketch,
+    toolchain,
+};
+use serde::Deserialize;
+use std::{fs, iter};
+use zed_extension_api::{
+    self as zed, process::Command, serde_json, BuildTaskDefinition,
+    BuildTaskDefinitionTemplatePayload, DebugAdapterBinary, DebugConfig, DebugRequest,
+    DebugScenario, DebugTaskDefinition, LaunchRequest, Result, StartDebuggingRequestArguments,
+    StartDebuggingRequestArgumentsRequest, TaskTemplate,
+};
+
+/// Name of the debug adapter in `extension.toml` and `debug.json`.
+pub const ADAPTER_NAME: &str = "Arduino";
+
+/// Name of the debug locator turning the verify and upload tasks into debug
+/// sessions.
+pub const LOCATOR_NAME: &str = "arduino";
+
+/// Compiles the sketch in `$1` with debug symbols and uploads it, for the
+/// board, port and programmer the tasks use.
+///
+/// Cores without debug optimization flags, like AVR, get `-Og -g` appended
+/// to their C and C++ extra flags instead, which their recipes pass after
+/// their own optimization flags. A warning is printed for cores without a
+/// debug recipe.
+const DEBUG_UPLOAD_SCRIPT: &str = r#"sketch="$1"
+set -- ${ARDUINO_PROFILE:+--profile "$ARDUINO_PROFILE"} ${ARDUINO_FQBN:+--fqbn "$ARDUINO_FQBN"}
+properties=$(arduino-cli compile --show-properties=expanded "$@" "$sketch") || exit
+case "$properties" in
+  *debug.executable=*|*debug.server=*) ;;
+  *) echo "warning: the core of the board has no debug recipe, it may not support debugging" >&2 ;;
+esac
+case "$properties" in
+  *compiler.optimization_flags.debug=*) set -- "$@" --optimize-for-debug ;;
+  *)
+    extra_flags() { printf '%s\n' "$properties" | sed -n "s/^compiler\\.$1\\.extra_flags=//p"; }
+    set -- "$@" --build-property "compiler.c.extra_flags=$(extra_flags c) -Og -g" \
+      --build-property "compiler.cpp.extra_flags=$(extra_flags cpp) -Og -g" ;;
+esac
+exec arduino-cli compile --upload "$@" ${ARDUINO_PORT:+--port "$ARDUINO_PORT"} ${ARDUINO_PROGRAMMER:+--programmer "$ARDUINO_PROGRAMMER"} "$sketch""#;
+
+/// Directory receiving the SVD files downloaded from the URLs of debug
+/// scenarios.
+const SVD_DIR: &str = "svd";
+
+/// The first GDB release with a DAP interpreter.
+const MIN_GDB_VERSION: u32 = 14;
+
+/// The adapter-specific part of an Arduino debug scenario in `debug.json`.
+#[derive(Deserialize)]
+struct DebugOptions {
+    request: Request,
+    /// The sketch folder, defaulting to the root of the worktree.
+    sketch: Option<String>,
+    fqbn: Option<String>,
+    programmer: Option<String>,
+    port: Option<String>,
+    /// The GDB server, `openocd`, `jlink` or `probe-rs`, defaulting to the one
+    /// of the platform's debug recipe.
+    server: Option<String>,
+    /// Server executable to use instead of the platform's.
+    server_path: Option<String>,
+    /// J-Link device or probe-rs chip name of the board's chip, like
+    /// `ATSAMD21G18A`.
+    device: Option<String>,
+    /// OpenOCD script of the debug probe, like `interface/picoprobe.cfg`,
+    /// replacing the one of the platform.
+    interface: Option<String>,
+    /// Clock speed of the debug probe in kHz.
+    adapter_speed: Option<u32>,
+    /// Address of an already running GDB server, like `localhost:3333`, to
+    /// connect to instead of starting one.
+    gdb_server: Option<String>,
+    /// Whether the semihosting output of the sketch is shown, with OpenOCD.
+    #[serde(default)]
+    semihosting: bool,
+    /// Whether the RTT output of the sketch is shown, with probe-rs.
+    #[serde(default)]
+    rtt: bool,
+    /// Path or URL of the chip's SVD file, describing its peripheral
+    /// registers.
+    svd_file: Option<String>,
+    /// GDB commands run when GDB starts, before it connects to the board.
+    #[serde(default)]
+    gdb_commands: Vec<String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Request {
+    /// Resets the board and halts it before the session starts.
+    Launch,
+    /// Halts the running sketch where it is.
+    Attach,
+}
+
+pub fn request_kind(config: &serde_json::Value) -> Result<StartDebuggingRequestArgumentsRequest> {
+    match config.get("request").and_then(|request| request.as_str()) {
+        Some("launch") => Ok(StartDebuggingRequestArgumentsRequest::Launch),
+        Some("attach") => Ok(StartDebuggingRequestArgumentsRequest::Attach),
+        Some(request) => Err(format!(
+            "unknown debug request `{request}`, expected `launch` or `attach`"
+        )),
+        None => Err("the debug scenario has no `request`".to_string()),
+    }
+}
+
+/// Expands a debug session started from Zed's UI to a scenario that compiles
+/// the sketch with debug symbols and uploads it before debugging it.
+///
+/// The program to launch is the sketch folder or its `.ino` file. The board,
+/// port and programmer come from the `ARDUINO_FQBN`, `ARDUINO_PORT` and
+/// `ARDUINO_PROGRAMMER` environment variables of the session, like for the
+/// tasks, and otherwise from the project. The debug probe is the one found
+/// attached, unless the sketch's `sketch.yaml` profile sets up another.
+pub fn scenario(config: DebugConfig) -> Result<DebugScenario> {
+    // Pre-fill the attached debug probe, so common setups need no configuration
+    let probe = ArduinoCli::for_worktree(None)
+        .ok()
+        .and_then(|cli| probes::detect(&cli));
+    let probe = probe.map(|probe| {
+        serde_json::json!({
+            "server": probe.server,
+            "interface": probe.interface,
+        })
+    });
+
+    let launch = match config.request {
+        DebugRequest::Launch(launch) => launch,
+        // Attaching debugs what already runs on the board, so nothing is built
+        DebugRequest::Attach(_) => {
+            return Ok(DebugScenario {
+                label: config.label,
+                adapter: config.adapter,
+                build: None,
+                config: serde_json::json!({
+                    "request": "attach",
+                    "probe": probe,
+                })
+                .to_string(),
+                tcp_connection: None,
+            });
+        }
+    };
+
+    let sketch = match launch.program.rsplit_once(['/', '\\']) {
+        Some((dir, _)) if launch.program.ends_with(".ino") => dir.to_string(),
+        _ => launch.program.clone(),
+    };
+    let env = |name: &str| {
+        launch
+            .envs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    };
+    let fqbn = env("ARDUINO_FQBN");
+    let port = env("ARDUINO_PORT");
+    let programmer = env("ARDUINO_PROGRAMMER");
+    let profile = env("ARDUINO_PROFILE");
+
+    let build = debug_upload_task(
+        format!("Arduino: Upload {} for debugging", host::file_name(&sketch)),
+        &sketch,
+        launch.envs,
+        launch.cwd,
+    );
+    Ok(DebugScenario {
+        label: config.label,
+        adapter: config.adapter,
+        build: Some(BuildTaskDefinition::Template(
+            BuildTaskDefinitionTemplatePayload {
+                locator_name: None,
+                template: build,
+            },
+        )),
+        config: serde_json::json!({
+            "request": "launch",
+            "sketch": sketch,
+            "fqbn": fqbn,
+            "port": port,
+            "programmer": programmer,
+            "profile": profile,
+            "probe": probe,
+        })
+        .to_string(),
+        tcp_connection: None,
+    })
+}
+
+/// Turns an arduino-cli verify or upload task into a debug session whose
+/// build step uploads the sketch compiled with debug symbols.
+pub fn locator_scenario(
+    build_task: &TaskTemplate,
+    resolved_label: &str,
+    adapter: &str,
+) -> Option<DebugScenario> {
+    let command = format!("{} {}", build_task.command, build_task.args.join(" "));
+    if adapter != ADAPTER_NAME
+        || !(command.contains("arduino-cli compile") || command.contains("arduino-cli upload"))
+    {
+        return None;
+    }
+
+    let build = debug_upload_task(
+        format!("{resolved_label} for debugging"),
+        "$ZED_DIRNAME",
+        build_task.env.clone(),
+        build_task.cwd.clone(),
+    );
+    Some(DebugScenario {
+        label: resolved_label.to_string(),
+        adapter: ADAPTER_NAME.to_string(),
+        build: Some(BuildTaskDefinition::Template(
+            BuildTaskDefinitionTemplatePayload {
+                locator_name: Some(LOCATOR_NAME.to_string()),
+                template: build,
+            },
+        )),
+        config: serde_json::json!({}).to_string(),
+        tcp_connection: None,
+    })
+}
+
+/// Returns the task running [`DEBUG_UPLOAD_SCRIPT`] for a sketch.
+fn debug_upload_task(
+    label: String,
+    sketch: &str,
+    env: Vec<(String, String)>,
+    cwd: Option<String>,
+) -> TaskTemplate {
+    TaskTemplate {
+        label,
+        command: "sh".to_string(),
+        args: vec![
+            "-c".to_string(),
+            DEBUG_UPLOAD_SCRIPT.to_string(),
+            "sh".to_string(),
+            sketch.to_string(),
+        ],
+        env,
+        cwd,
+    }
+}
+
+/// Launches the sketch the build step of [`locator_scenario`] uploaded.
+pub fn run_locator(build_task: TaskTemplate) -> Result<DebugRequest> {
+    let sketch = build_task
+        .args
+        .last()
+        .cloned()
+        .ok_or_else(|| "the debug build task has no sketch".to_string())?;
+    Ok(DebugRequest::Launch(LaunchRequest {
+        program: sketch,
+        cwd: build_task.cwd,
+        args: Vec::new(),
+        envs: build_task.env,
+    }))
+}
+
+/// Resolves a debug scenario to GDB's DAP interpreter, connected to the GDB
+/// server, or to probe-rs.
+///
+/// Where the sketch's ELF file is and how to configure the GDB server comes
+/// from the platform's debug recipe, as reported by `arduino-cli debug
+/// --info`, or for boards without one from the chip they are known to use.
+pub fn binary(
+    config: &DebugTaskDefinition,
+    gdb_path: Option<String>,
+    worktree: &zed::Worktree,
+) -> Result<DebugAdapterBinary> {
+    let options = debug_options(&config.config, worktree)?;
+    let settings = ResolvedSettings::for_worktree(worktree)?;
+
+    let sketch = options
+        .sketch
+        .clone()
+        .unwrap_or_else(|| worktree.root_path());
+    let fqbn = options
+        .fqbn
+        .clone()
+        .or_else(|| sketch::active_fqbn(worktree, &settings.arduino))
+        .ok_or_else(|| {
+            "no board to debug, set `fqbn` in the debug scenario or run /arduino-fqbn".to_string()
+        })?;
+    let programmer = options
+        .programmer
+        .clone()
+        .or_else(|| sketch::sketch_yaml_value(worktree, "default_programmer"));
+    let port = options.port.clone().or(settings.arduino.port.clone());
+
+    let cli = ArduinoCli::new(worktree, &settings)?;
+    let fqbn = with_jtag_adapter(&cli, fqbn);
+
+    let mut args = vec!["debug", "--info", "--fqbn", &fqbn];
+    if let Some(programmer) = &programmer {
+        args.extend(["--programmer", programmer]);
+    }
+    if let Some(port) = &port {
+        args.extend(["--port", port]);
+    }
+    args.push(&sketch);
+    let info = match cli.run_json(&args) {
+        Ok(info) => info,
+        Err(e) => fallback_info(&cli, &fqbn, &sketch, options.server.as_deref())
+            .ok_or_else(|| format!("the core of {fqbn} doesn't support debugging it: {e}"))?,
+    };
+
+    let executable = info["executable"]
+        .as_str()
+        .ok_or_else(|| format!("arduino-cli reported no executable to debug for {fqbn}"))?;
+    let server = options
+        .server
+        .as_deref()
+        .or_else(|| info["server"].as_str())
+        .unwrap_or_default();
+
+    // probe-rs is a debug adapter of its own
+    if server == "probe-rs" {
+        let probe_rs = options
+            .server_path
+            .clone()
+            .or_else(|| host::which(worktree, "probe-rs"))
+            .ok_or_else(|| {
+                "probe-rs not found, install it or set `server_path` in the debug scenario"
+                    .to_string()
+            })?;
+        let chip = options
+            .device
+            .clone()
+            .or_else(|| probe_rs::chip(&fqbn))
+            .ok_or_else(|| {
+                format!("unknown probe-rs chip of {fqbn}, set `device` in the debug scenario")
+            })?;
+        let svd_file = svd_file(options.svd_file.as_deref(), &info)?;
+        let target = probe_rs::Target {
+            chip: &chip,
+            executable,
+            svd_file: svd_file.as_deref(),
+            speed: options.adapter_speed,
+            rtt: options.rtt,
+        };
+        return probe_rs::binary(
+            probe_rs,
+            &target,
+            sketch,
+            matches!(options.request, Request::Launch),
+        );
+    }
+
+    let gdb = match gdb_path {
+        Some(gdb_path) => gdb_path,
+        None => find_gdb(&info, &fqbn, worktree)?,
+    };
+    let (mut arguments, target) = match (&options.gdb_server, server) {
+        (Some(gdb_server), _) => (Vec::new(), gdb_server.clone()),
+        (None, "openocd") => (
+            Vec::new(),
+            openocd_target(&info, &fqbn, &options, worktree)?,
+        ),
+        (None, "jlink") => {
+            let path = jlink::locate(options.server_path.clone(), worktree)?;
+            let device = match &options.device {
+                Some(device) => device.clone(),
+                None => build_properties(&cli, &fqbn, &sketch)
+                    .ok()
+                    .and_then(|properties| {
+                        jlink::device(&property(&properties, "recipe.cpp.o.pattern")?)
+                    })
+                    .ok_or_else(|| {
+                        format!("unknown J-Link device name of {fqbn}, set `device` in the debug scenario")
+                    })?,
+            };
+            let reset = matches!(options.request, Request::Launch);
+            let start = jlink::start_command(&path, &device, options.adapter_speed, reset);
+            (vec!["-iex".to_string(), start], jlink::target())
+        }
+        _ => {
+            return Err(format!(
+                "debugging with the `{server}` GDB server isn't supported"
+            ))
+        }
+    };
+    for command in &options.gdb_commands {
+        arguments.extend(["-ex".to_string(), command.clone()]);
+    }
+    arguments.push("--interpreter=dap".to_string());
+
+    let configuration = serde_json::json!({
+        "request": "attach",
+        "program": executable,
+        "target": target,
+    });
+
+    Ok(DebugAdapterBinary {
+        command: Some(gdb),
+        arguments,
+        envs: Vec::new(),
+        cwd: Some(sketch),
+        connection: None,
+        request_args: StartDebuggingRequestArguments {
+            configuration: configuration.to_string(),
+            request: StartDebuggingRequestArgumentsRequest::Attach,
+        },
+    })
+}
+
+/// Parses a debug scenario. What it leaves unset comes from the `debug`
+/// settings of the sketch's `sketch.yaml` profile, then from the rest of the
+/// profile, and then from the probe found attached when it was generated.
+fn debug_options(config: &str, worktree: &zed::Worktree) -> Result<DebugOptions> {
+    let invalid = |e: serde_json::Error| format!("invalid {ADAPTER_NAME} debug scenario: {e}");
+    let serde_json::Value::Object(mut scenario) = serde_json::from_str(config).map_err(invalid)?
+    else {
+        return Err(format!(
+            "invalid {ADAPTER_NAME} debug scenario: expected an object"
+        ));
+    };
+
+    let mut options = match scenario.remove("probe") {
+        Some(serde_json::Value::Object(probe)) => probe,
+        _ => serde_json::Map::new(),
+    };
+    let profile_name = scenario.get("profile").and_then(|profile| profile.as_str());
+    if let Some(mut profile) = sketch::profile(worktree, profile_name) {
+        for key in ["fqbn", "programmer"] {
+            if let Some(value) = profile.remove(key) {
+                options.insert(key.to_string(), value);
+            }
+        }
+        if let Some(serde_json::Value::Object(debug)) = profile.remove("debug") {
+            options.extend(debug);
+        }
+    }
+    // Generated scenarios leave what they don't know as `null`
+    options.extend(scenario.into_iter().filter(|(_, value)| !value.is_null()));
+
+    serde_json::from_value(serde_json::Value::Object(options)).map_err(invalid)
+}
+
+/// Selects the JTAG adapter of ESP32 boards, whose platform only has a debug
+/// recipe when one is chosen in the `JTAGAdapter` board option: the USB JTAG
+/// built into the ESP32-S3, C3, C6 and H2, or otherwise an external one.
+fn with_jtag_adapter(cli: &ArduinoCli, fqbn: String) -> String {
+    if fqbn.split(':').nth(1) != Some("esp32") || fqbn.contains("JTAGAdapter=") {
+        return fqbn;
+    }
+    let Ok(details) = cli.run_json(&["board", "details", "--fqbn", &fqbn]) else {
+        return fqbn;
+    };
+    let values: Vec<&str> = details["config_options"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|option| option["option"] == "JTAGAdapter")
+        .flat_map(|option| option["values"].as_array().into_iter().flatten())
+        .filter_map(|value| value["value"].as_str())
+        .collect();
+    let Some(adapter) = ["builtin", "external"]
+        .into_iter()
+        .find(|adapter| values.contains(adapter))
+    else {
+        return fqbn;
+    };
+    // Board options follow the board ID after a third colon, separated by commas
+    let separator = if fqbn.matches(':').count() > 2 {
+        ','
+    } else {
+        ':'
+    };
+    format!("{fqbn}{separator}JTAGAdapter={adapter}")
+}
+
+/// Returns the `target remote` argument starting OpenOCD with GDB talking
+/// to it over its standard input and output.
+fn openocd_target(
+    info: &serde_json::Value,
+    fqbn: &str,
+    options: &DebugOptions,
+    worktree: &zed::Worktree,
+) -> Result<String> {
+    let server_config = &info["server_configuration"];
+    // The platform's OpenOCD is only used with its own debug recipe
+    let platform_openocd = (info["server"] == "openocd")
+        .then(|| {
+            info["server_path"]
+                .as_str()
+                .or_else(|| server_config["path"].as_str())
+        })
+        .flatten();
+    let openocd = openocd::locate(
+        options.server_path.clone(),
+        platform_openocd,
+        fqbn,
+        worktree,
+    )?;
+
+    let scripts: Vec<String> = server_config["scripts"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|script| script.as_str().map(ToString::to_string))
+        .collect();
+    let config = openocd::write_config(
+        fqbn,
+        &scripts,
+        options.interface.as_deref(),
+        options.adapter_speed,
+    )?;
+
+    let mut target = format!("| \"{}\"", openocd.path);
+    // The platform's scripts may source its own chip configurations
+    for scripts_dir in [
+        openocd.scripts_dir.as_deref(),
+        server_config["scripts_dir"].as_str(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        target.push_str(&format!(" -s \"{scripts_dir}\""));
+    }
+    target.push_str(&format!(" -f \"{config}\""));
+    target.push_str(" -c \"gdb_port pipe; telnet_port disabled; tcl_port disabled\"");
+
+    let mut commands = Vec::new();
+    if let Request::Launch = options.request {
+        commands.push("reset halt");
+    }
+    // Semihosting output is passed on to GDB, which writes it to the debug console
+    if options.semihosting {
+        commands.extend(["arm semihosting enable", "arm semihosting_fileio enable"]);
+    }
+    if !commands.is_empty() {
+        target.push_str(&format!(" -c \"init; {}\"", commands.join("; ")));
+    }
+    Ok(target)
+}
+
+/// Returns the SVD file of the board's chip: the one of the scenario, which
+/// is downloaded to the extension's working directory if it is a URL, or the
+/// one of the platform.
+fn svd_file(configured: Option<&str>, info: &serde_json::Value) -> Result<Option<String>> {
+    let Some(svd_file) = configured.or_else(|| info["svd_file"].as_str()) else {
+        return Ok(None);
+    };
+    if !svd_file.starts_with("http://") && !svd_file.starts_with("https://") {
+        return Ok(Some(svd_file.to_string()));
+    }
+
+    let path = format!("{SVD_DIR}/{}", host::file_name(svd_file));
+    if !fs::metadata(&path).is_ok_and(|stat| stat.is_file()) {
+        fs::create_dir_all(SVD_DIR)
+            .map_err(|e| format!("failed to create {SVD_DIR} directory: {e}"))?;
+        zed::download_file(svd_file, &path, zed::DownloadedFileType::Uncompressed)
+            .map_err(|e| format!("failed to download {svd_file}: {e}"))?;
+    }
+    host::work_dir_path(&path).map(Some)
+}
+
+/// Builds the debug information of a board whose platform has no debug
+/// recipe from the sketch's build properties, for a chosen GDB server or
+/// when OpenOCD knows the board's chip.
+fn fallback_info(
+    cli: &ArduinoCli,
+    fqbn: &str,
+    sketch: &str,
+    server: Option<&str>,
+) -> Option<serde_json::Value> {
+    let scripts = openocd::default_scripts(fqbn);
+    let server = server.or(scripts.is_some().then_some("openocd"))?;
+    let properties = build_properties(cli, fqbn, sketch).ok()?;
+    let executable = host::join(
+        &property(&properties, "build.path")?,
+        &format!("{}.elf", property(&properties, "build.project_name")?),
+    );
+    Some(serde_json::json!({
+        "executable": executable,
+        "server": server,
+        "server_configuration": { "scripts": scripts.unwrap_or_default() },
+    }))
+}
+
+/// Returns the expanded build properties of the sketch for `fqbn`.
+fn build_properties(cli: &ArduinoCli, fqbn: &str, sketch: &str) -> Result<String> {
+    cli.run(&[
+        "compile",
+        "--show-properties=expanded",
+        "--fqbn",
+        fqbn,
+        sketch,
+    ])
+}
+
+/// Looks up a property in the output of `--show-properties`.
+fn property(properties: &str, name: &str) -> Option<String> {
+    properties.lines().find_map(|line| {
+        line.strip_prefix(name)?
+            .strip_prefix('=')
+            .map(ToString::to_string)
+    })
+}
+
+/// Finds a GDB with a DAP interpreter: the one of the platform's toolchain,
+/// a multi-architecture GDB on the `PATH`, or otherwise for ARM boards the
+/// one of the xPack Arm toolchain, which is downloaded.
+fn find_gdb(info: &serde_json::Value, fqbn: &str, worktree: &zed::Worktree) -> Result<String> {
+    let suffix = match zed::current_platform().0 {
+        zed::Os::Mac | zed::Os::Linux => "",
+        zed::Os::Windows => ".exe",
+    };
+    let toolchain_gdb = info["toolchain_path"]
+        .as_str()
+        .zip(info["toolchain_prefix"].as_str())
+        .map(|(path, prefix)| host::join(path, &format!("{prefix}-gdb{suffix}")));
+
+    toolchain_gdb
+        .into_iter()
+        .chain(
+            ["gdb-multiarch", "gdb"]
+                .into_iter()
+                .filter_map(|name| host::which(worktree, name)),
+        )
+        // Only downloaded when no other GDB will do
+        .chain(
+            iter::once_with(|| is_arm(info, fqbn).then(download_gdb)?.ok()).flatten(),
+        )
+        .find(|gdb| gdb_version(gdb).is_some_and(|version| version >= MIN_GDB_VERSION))
+        .ok_or_else(|| {
+            format!(
+                "no GDB {MIN_GDB_VERSION} or newer found, install gdb-multiarch or set `dap.{ADAPTER_NAME}.binary` in the settings"
+            )
+        })
+}
+
+/// Returns whether the board has an ARM chip, going by the platform's
+/// toolchain or its architecture.
+fn is_arm(info: &serde_json::Value, fqbn: &str) -> bool {
+    info["toolchain_prefix"] == "arm-none-eabi"
+        || matches!(
+            fqbn.split(':').nth(1),
+            Some("samd" | "sam" | "rp2040" | "mbed_rp2040" | "nrf52" | "mbed_nano" | "stm32")
+        )
+}
+
+/// Downloads the latest xPack Arm toolchain for its GDB, unless it already
+/// was.
+fn download_gdb() -> Result<String> {
+    let (platform, arch) = zed::current_platform();
+    let (os, extension, suffix) = match platform {
+        zed::Os::Mac => ("darwin", "tar.gz", ""),
+        zed::Os::Linux => ("linux", "tar.gz", ""),
+        zed::Os::Windows => ("win32", "zip", ".exe"),
+    };
+    let arch = match arch {
+        zed::Architecture::Aarch64 => "arm64",
+        zed::Architecture::X86 | zed::Architecture::X8664 => "x64",
+    };
+    let executable = |installed: &toolchain::Installed| {
+        format!(
+            "{}/xpack-arm-none-eabi-gcc-{}/bin/arm-none-eabi-gdb{suffix}",
+            installed.dir, installed.version
+        )
+    };
+    let tool = toolchain::Tool {
+        name: "GDB",
+        repository: "xpack-dev-tools/arm-none-eabi-gcc-xpack",
+        dir_prefix: "arm-none-eabi-gcc-xpack-",
+    };
+    let installed = toolchain::install(
+        &tool,
+        |version| format!("xpack-arm-none-eabi-gcc-{version}-{os}-{arch}.{extension}"),
+        executable,
+    )?;
+    host::work_dir_path(&executable(&installed))
+}
+
+/// Returns the major version of a GDB executable.
+fn gdb_version(gdb: &str) -> Option<u32> {
+    let output = Command::new(gdb)
+        .arg("--version")
+        .output()
+        .inspect_err(|_| host::forget_tool(gdb))
+        .ok()?;
+    // The first line ends in the version, like `GNU gdb (GDB) 14.2`
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .split_whitespace()
+        .last()?
+        .split('.')
+        .next()?
+        .parse()
+        .ok()
+}
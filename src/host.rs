@@ -0,0 +1,203 @@
+use std::{cell::RefCell, collections::HashMap, env};
+use zed_extension_api::{self as zed, process::Command, Result};
+
+thread_local! {
+    // Shell environment of each worktree, as getting it spawns a login shell
+    static SHELL_ENVS: RefCell<HashMap<u64, Vec<(String, String)>>> = RefCell::new(HashMap::new());
+    // Paths of the tools found on each worktree's PATH, since looking them up
+    // is slow with long PATHs and network home directories
+    static TOOLS: RefCell<HashMap<(u64, String), String>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the environment of the worktree's login shell, fetched once and
+/// then reused until [`refresh_shell_env`] fetches it again.
+pub fn shell_env(worktree: &zed::Worktree) -> Vec<(String, String)> {
+    match SHELL_ENVS.with_borrow(|envs| envs.get(&worktree.id()).cloned()) {
+        Some(env) => env,
+        None => refresh_shell_env(worktree),
+    }
+}
+
+/// Fetches the environment of the worktree's login shell again, to pick up
+/// changes like a newly allowed `.envrc`.
+pub fn refresh_shell_env(worktree: &zed::Worktree) -> Vec<(String, String)> {
+    let env = worktree.shell_env();
+    SHELL_ENVS.with_borrow_mut(|envs| envs.insert(worktree.id(), env.clone()));
+    env
+}
+
+/// Looks up a tool on the worktree's `PATH`, reusing the path found before
+/// until [`forget_tools`] or [`forget_tool`] drops it.
+pub fn which(worktree: &zed::Worktree, name: &str) -> Option<String> {
+    let key = (worktree.id(), name.to_string());
+    if let Some(path) = TOOLS.with_borrow(|tools| tools.get(&key).cloned()) {
+        return Some(path);
+    }
+    let path = worktree.which(name)?;
+    TOOLS.with_borrow_mut(|tools| tools.insert(key, path.clone()));
+    Some(path)
+}
+
+/// Forgets the tool paths found on the worktree's `PATH`, so tools that were
+/// installed, moved or removed since are looked up again.
+pub fn forget_tools(worktree: &zed::Worktree) {
+    TOOLS.with_borrow_mut(|tools| tools.retain(|(id, _), _| *id != worktree.id()));
+}
+
+/// Forgets a tool path found by [`which`] after running it failed, so it is
+/// looked up again next time.
+pub fn forget_tool(path: &str) {
+    TOOLS.with_borrow_mut(|tools| tools.retain(|_, tool| tool != path));
+}
+
+/// Reads a text file from the host file system.
+///
+/// The extension itself can only access its own working directory, so this
+/// goes through the platform's shell utilities instead.
+pub fn read_text_file(path: &str) -> Result<String> {
+    let mut command = match zed::current_platform().0 {
+        zed::Os::Mac | zed::Os::Linux => Command::new("cat").arg(path),
+        // cmd would parse the path again, running what follows a `&` in it
+        zed::Os::Windows => Command::new("powershell").args([
+            "-NoProfile".to_string(),
+            "-Command".to_string(),
+            format!(
+                "[Console]::OutputEncoding = [Text.Encoding]::UTF8; \
+                 [Console]::Out.Write((Get-Content -Raw -Encoding utf8 -LiteralPath {}))",
+                quote_powershell(path)
+            ),
+        ]),
+    };
+    let output = command
+        .output()
+        .map_err(|e| format!("failed to read {path}: {e}"))?;
+
+    if output.status != Some(0) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("failed to read {path}: {}", stderr.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Writes a text file to the host file system, replacing its contents.
+pub fn write_text_file(path: &str, contents: &str) -> Result<()> {
+    let mut command = match zed::current_platform().0 {
+        // Pass the contents and path as arguments, so the shell never interprets them
+        zed::Os::Mac | zed::Os::Linux => {
+            Command::new("sh").args(["-c", "printf '%s' \"$1\" > \"$2\"", "sh", contents, path])
+        }
+        zed::Os::Windows => Command::new("powershell").args([
+            "-NoProfile".to_string(),
+            "-Command".to_string(),
+            format!(
+                "Set-Content -NoNewline -Encoding utf8 -LiteralPath {} -Value {}",
+                quote_powershell(path),
+                quote_powershell(contents)
+            ),
+        ]),
+    };
+    let output = command
+        .output()
+        .map_err(|e| format!("failed to write {path}: {e}"))?;
+
+    if output.status != Some(0) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("failed to write {path}: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Creates a directory on the host file system, failing if it already exists.
+pub fn create_dir(path: &str) -> Result<()> {
+    let mut command = match zed::current_platform().0 {
+        zed::Os::Mac | zed::Os::Linux => Command::new("mkdir").arg(path),
+        zed::Os::Windows => Command::new("powershell").args([
+            "-NoProfile".to_string(),
+            "-Command".to_string(),
+            format!(
+                "New-Item -ItemType Directory -Path {} | Out-Null",
+                quote_powershell(path)
+            ),
+        ]),
+    };
+    let output = command
+        .output()
+        .map_err(|e| format!("failed to create {path}: {e}"))?;
+
+    if output.status != Some(0) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("failed to create {path}: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Recursively lists the files with the given extension in a host directory.
+pub fn list_files(dir: &str, extension: &str) -> Result<Vec<String>> {
+    let pattern = format!("*.{extension}");
+    let mut command = match zed::current_platform().0 {
+        zed::Os::Mac | zed::Os::Linux => {
+            Command::new("find").args([dir, "-type", "f", "-name", &pattern])
+        }
+        zed::Os::Windows => Command::new("powershell").args([
+            "-NoProfile".to_string(),
+            "-Command".to_string(),
+            format!(
+                "[Console]::OutputEncoding = [Text.Encoding]::UTF8; \
+                 Get-ChildItem -LiteralPath {} -Recurse -File -Filter {} | \
+                 Where-Object {{ $_.Extension -eq {} }} | ForEach-Object {{ $_.FullName }}",
+                quote_powershell(dir),
+                quote_powershell(&pattern),
+                quote_powershell(&format!(".{extension}"))
+            ),
+        ]),
+    };
+    let output = command
+        .output()
+        .map_err(|e| format!("failed to list {dir}: {e}"))?;
+
+    // Listing a missing directory fails
+    let mut files: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| line.trim_end().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Quotes a value for PowerShell, which doesn't interpret anything inside
+/// single quotes.
+pub(crate) fn quote_powershell(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Joins a file name onto a host path, using the separator the path already
+/// uses.
+pub fn join(path: &str, file_name: &str) -> String {
+    let separator = if path.contains('\\') { '\\' } else { '/' };
+    format!(
+        "{}{separator}{file_name}",
+        path.trim_end_matches(['/', '\\'])
+    )
+}
+
+/// Returns the last component of a host path.
+pub fn file_name(path: &str) -> &str {
+    path.trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(path)
+}
+
+/// Turns a path inside the extension's working directory into an absolute
+/// one, for the processes that don't run in it.
+pub fn work_dir_path(path: &str) -> Result<String> {
+    Ok(env::current_dir()
+        .map_err(|e| format!("failed to get working directory: {e}"))?
+        .join(path)
+        .to_string_lossy()
+        .into_owned())
+}
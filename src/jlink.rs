@@ -0,0 +1,66 @@
+use crate::host;
+use zed_extension_api::{self as zed, Result};
+
+/// Port the J-Link GDB server listens on for GDB.
+const GDB_PORT: u16 = 2331;
+
+/// Finds the command-line J-Link GDB server of SEGGER's J-Link software.
+pub fn locate(configured: Option<String>, worktree: &zed::Worktree) -> Result<String> {
+    configured
+        .or_else(|| {
+            ["JLinkGDBServerCLExe", "JLinkGDBServerCL", "JLinkGDBServer"]
+                .into_iter()
+                .find_map(|name| host::which(worktree, name))
+        })
+        .ok_or_else(|| {
+            "JLinkGDBServer not found, install the J-Link software or set `server_path` in the debug scenario"
+                .to_string()
+        })
+}
+
+/// Derives the J-Link device name of a board's chip from the defines its
+/// platform compiles with, like `-D__SAMD21G18A__` or `-DNRF52840_XXAA`.
+pub fn device(compile_command: &str) -> Option<String> {
+    compile_command
+        .split_whitespace()
+        .filter_map(|flag| flag.trim_matches('"').strip_prefix("-D"))
+        .find_map(|define| {
+            let define = define.split('=').next()?;
+            if let Some(chip) = define
+                .strip_prefix("__SAM")
+                .and_then(|define| define.strip_suffix("__"))
+            {
+                return Some(format!("ATSAM{chip}"));
+            }
+            if let Some(chip) = define
+                .strip_prefix("NRF52")
+                .and_then(|define| define.strip_suffix("_XXAA"))
+            {
+                return Some(format!("nRF52{chip}_xxAA"));
+            }
+            (define == "ARDUINO_ARCH_RP2040").then(|| "RP2040_M0_0".to_string())
+        })
+}
+
+/// Returns the GDB command starting the J-Link GDB server in the background.
+/// It exits once GDB disconnects.
+pub fn start_command(path: &str, device: &str, adapter_speed: Option<u32>, reset: bool) -> String {
+    let speed = adapter_speed.map_or("auto".to_string(), |speed| speed.to_string());
+    let mut command = format!(
+        "\"{path}\" -device {device} -if SWD -speed {speed} -port {GDB_PORT} -singlerun -nogui -silent"
+    );
+    if reset {
+        command.push_str(" -ir");
+    }
+    // The server's output would end up in the DAP messages GDB writes
+    match zed::current_platform().0 {
+        zed::Os::Mac | zed::Os::Linux => format!("shell {command} >/dev/null 2>&1 &"),
+        zed::Os::Windows => format!("shell start \"\" /B {command} >NUL 2>&1"),
+    }
+}
+
+/// Returns the `target remote` argument connecting to the J-Link GDB server.
+/// GDB retries until the server is up.
+pub fn target() -> String {
+    format!("localhost:{GDB_PORT}")
+}
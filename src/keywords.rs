@@ -0,0 +1,610 @@
+use std::collections::BTreeMap;
+
+/// The categories of `keywords.txt`, coloring the identifiers in the Arduino
+/// IDE.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    /// Classes, datatypes and the objects a library defines.
+    Keyword1,
+    /// Methods and functions.
+    Keyword2,
+    /// Constants.
+    Literal1,
+}
+
+impl Category {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Keyword1 => "KEYWORD1",
+            Self::Keyword2 => "KEYWORD2",
+            Self::Literal1 => "LITERAL1",
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            Self::Keyword1 => "Datatypes (KEYWORD1)",
+            Self::Keyword2 => "Methods and Functions (KEYWORD2)",
+            Self::Literal1 => "Constants (LITERAL1)",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "KEYWORD1" => Some(Self::Keyword1),
+            "KEYWORD2" => Some(Self::Keyword2),
+            "LITERAL1" => Some(Self::Literal1),
+            _ => None,
+        }
+    }
+}
+
+/// The identifiers of a `keywords.txt` file, by name.
+#[derive(Default)]
+pub struct Keywords(BTreeMap<String, Category>);
+
+impl Keywords {
+    /// Reads the entries of an existing `keywords.txt`. Its categories win
+    /// over the ones found in the headers.
+    pub fn parse(keywords_txt: &str) -> Self {
+        let mut keywords = Self::default();
+        for line in keywords_txt.lines() {
+            let mut fields = line.split('\t');
+            let (Some(name), Some(category)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            if let Some(category) = Category::parse(category.trim()) {
+                keywords.0.insert(name.trim().to_string(), category);
+            }
+        }
+        keywords
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn add(&mut self, name: &str, category: Category) {
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        // Names starting with an underscore are reserved or private
+        if is_identifier && !name.starts_with('_') && !KEYWORDS.contains(&name) {
+            self.0.entry(name.to_string()).or_insert(category);
+        }
+    }
+
+    /// Adds the public classes, functions and constants a header declares.
+    pub fn scan_header(&mut self, source: &str) {
+        let source = strip_comments(source);
+        let mut code = String::new();
+        let mut lines = source.lines();
+        while let Some(line) = lines.next() {
+            let trimmed = line.trim_start();
+            if !trimmed.starts_with('#') {
+                code.push_str(line);
+                code.push('\n');
+                continue;
+            }
+            // Skip the continuation lines of multi-line directives
+            let mut last = line;
+            while last.trim_end().ends_with('\\') {
+                let Some(next) = lines.next() else { break };
+                last = next;
+            }
+            self.scan_define(trimmed);
+        }
+        self.scan_declarations(&tokenize(&code));
+    }
+
+    /// Adds the constant an object-like `#define` declares, skipping include
+    /// guards and function-like macros.
+    fn scan_define(&mut self, directive: &str) {
+        let Some(rest) = directive
+            .strip_prefix('#')
+            .map(str::trim_start)
+            .and_then(|rest| rest.strip_prefix("define"))
+        else {
+            return;
+        };
+        let rest = rest.trim_start();
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let (name, value) = rest.split_at(name_len);
+        if !value.starts_with('(') && !value.trim().is_empty() {
+            self.add(name, Category::Literal1);
+        }
+    }
+
+    fn scan_declarations(&mut self, tokens: &[String]) {
+        let mut scopes: Vec<Scope> = Vec::new();
+        // Depths of the scopes opened inside a `typedef`, whose name follows the `}`
+        let mut typedefs: Vec<usize> = Vec::new();
+        // Tokens of the declaration being read, since the last `;`, `{` or `}`
+        let mut statement: Vec<&str> = Vec::new();
+        let mut index = 0;
+        while index < tokens.len() {
+            let token = tokens[index].as_str();
+            index += 1;
+            let visible = scopes.iter().all(Scope::is_visible);
+            let in_enum = matches!(scopes.last(), Some(Scope::Enum));
+
+            match token {
+                "{" => {
+                    let scope = match statement.iter().rposition(|token| {
+                        matches!(*token, "class" | "struct" | "union" | "enum" | "namespace")
+                    }) {
+                        Some(start) => {
+                            let keyword = match statement[start] {
+                                _ if start > 0 && statement[start - 1] == "enum" => "enum",
+                                keyword => keyword,
+                            };
+                            let name = statement
+                                .get(start + 1)
+                                .filter(|name| is_identifier(name))
+                                .map(ToString::to_string);
+                            if let (Some(name), true) = (&name, visible) {
+                                if keyword != "namespace" {
+                                    self.add(name, Category::Keyword1);
+                                }
+                            }
+                            match keyword {
+                                "enum" => Scope::Enum,
+                                "namespace" => Scope::Namespace,
+                                keyword => Scope::Class {
+                                    name: name.unwrap_or_default(),
+                                    public: keyword != "class",
+                                },
+                            }
+                        }
+                        None => Scope::Other,
+                    };
+                    if statement.first() == Some(&"typedef") {
+                        typedefs.push(scopes.len());
+                    }
+                    scopes.push(scope);
+                    statement.clear();
+                }
+                "}" => {
+                    // The last enumerator may have no trailing comma
+                    if let (true, true, Some(name)) = (in_enum, visible, statement.first()) {
+                        self.add(name, Category::Literal1);
+                    }
+                    scopes.pop();
+                    statement.clear();
+                    if typedefs.last() == Some(&scopes.len()) {
+                        typedefs.pop();
+                        statement.push("typedef");
+                    }
+                }
+                "," if in_enum => {
+                    if let (true, Some(name)) = (visible, statement.first()) {
+                        self.add(name, Category::Literal1);
+                    }
+                    statement.clear();
+                }
+                ";" => {
+                    if visible && !in_enum {
+                        self.scan_statement(&statement);
+                    }
+                    statement.clear();
+                }
+                ":" if matches!(
+                    statement.as_slice(),
+                    ["public"] | ["private"] | ["protected"]
+                ) =>
+                {
+                    if let Some(Scope::Class { public, .. }) = scopes.last_mut() {
+                        *public = statement[0] == "public";
+                    }
+                    statement.clear();
+                }
+                "(" => {
+                    if let (true, false, Some(name)) = (visible, in_enum, statement.last()) {
+                        let constructor = matches!(
+                            scopes.last(),
+                            Some(Scope::Class { name: class, .. }) if class == name
+                        );
+                        let destructor =
+                            statement.len() > 1 && statement[statement.len() - 2] == "~";
+                        // Member initializers of constructors, operators and initialized variables
+                        let declares_function = !["operator", "=", ":", "()"]
+                            .iter()
+                            .any(|token| statement.contains(token));
+                        if !constructor && !destructor && declares_function {
+                            self.add(name, Category::Keyword2);
+                        }
+                    }
+                    // Skip the parameters
+                    let mut depth = 1;
+                    while depth > 0 && index < tokens.len() {
+                        match tokens[index].as_str() {
+                            "(" => depth += 1,
+                            ")" => depth -= 1,
+                            _ => {}
+                        }
+                        index += 1;
+                    }
+                    statement.push("()");
+                }
+                _ => statement.push(token),
+            }
+        }
+    }
+
+    /// Adds what a declaration ending in `;` declares: a type alias, an
+    /// object of the library, or a constant.
+    fn scan_statement(&mut self, statement: &[&str]) {
+        let Some(first) = statement.first() else {
+            return;
+        };
+        if statement.contains(&"()") {
+            return;
+        }
+        match *first {
+            "typedef" => {
+                if let Some(name) = statement.last() {
+                    self.add(name, Category::Keyword1);
+                }
+            }
+            "using" if statement.get(2) == Some(&"=") => {
+                self.add(statement[1], Category::Keyword1);
+            }
+            // Objects the library defines, like `extern TwoWire Wire;`
+            "extern" => {
+                if let Some(name) = statement.last() {
+                    self.add(name, Category::Keyword1);
+                }
+            }
+            // Constants are named in capitals, unlike `const` members
+            _ if statement.contains(&"const") || statement.contains(&"constexpr") => {
+                let end = statement
+                    .iter()
+                    .position(|token| *token == "=")
+                    .unwrap_or(statement.len());
+                if let Some(name) = statement[..end]
+                    .last()
+                    .filter(|name| !name.chars().any(|c| c.is_ascii_lowercase()))
+                {
+                    self.add(name, Category::Literal1);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Renders the keywords in the layout of the Arduino IDE's libraries.
+    pub fn render(&self, library: &str) -> String {
+        const RULE: &str = "#######################################";
+        let mut text = format!("{RULE}\n# Syntax Coloring Map For {library}\n{RULE}\n");
+        for category in [Category::Keyword1, Category::Keyword2, Category::Literal1] {
+            text.push_str(&format!("\n{RULE}\n# {}\n{RULE}\n\n", category.heading()));
+            for (name, _) in self.0.iter().filter(|(_, found)| **found == category) {
+                text.push_str(&format!("{name}\t{}\n", category.name()));
+            }
+        }
+        text
+    }
+}
+
+/// What a pair of braces encloses.
+enum Scope {
+    Namespace,
+    Class {
+        name: String,
+        public: bool,
+    },
+    Enum,
+    /// Function bodies and initializers.
+    Other,
+}
+
+impl Scope {
+    /// Whether what the scope declares is part of the library's interface.
+    fn is_visible(&self) -> bool {
+        match self {
+            Self::Class { public, .. } => *public,
+            Self::Namespace | Self::Enum => true,
+            Self::Other => false,
+        }
+    }
+}
+
+/// C++ keywords, which are never library keywords.
+const KEYWORDS: &[&str] = &[
+    "const",
+    "constexpr",
+    "static",
+    "inline",
+    "virtual",
+    "explicit",
+    "extern",
+    "void",
+    "int",
+    "char",
+    "bool",
+    "float",
+    "double",
+    "long",
+    "short",
+    "unsigned",
+    "signed",
+    "auto",
+    "return",
+    "template",
+    "typename",
+    "class",
+    "struct",
+    "union",
+    "enum",
+    "namespace",
+    "public",
+    "private",
+    "protected",
+    "friend",
+    "operator",
+    "override",
+    "final",
+    "volatile",
+    "sizeof",
+    "if",
+    "while",
+    "for",
+    "switch",
+    "using",
+    "typedef",
+    "noexcept",
+];
+
+fn is_identifier(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+}
+
+/// Removes the comments of C++ source, leaving the line breaks in place.
+fn strip_comments(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => while chars.next_if(|c| *c != '\n').is_some() {},
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        stripped.push('\n');
+                    }
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            ('"' | '\'', _) => {
+                // Keep strings, so comment markers inside them are ignored
+                stripped.push(c);
+                let mut escaped = false;
+                for next in chars.by_ref() {
+                    stripped.push(next);
+                    if next == c && !escaped {
+                        break;
+                    }
+                    escaped = next == '\\' && !escaped;
+                }
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// Splits C++ source into identifiers and punctuation, dropping literals.
+fn tokenize(code: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = code.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_alphabetic() || c == '_' {
+            let mut identifier = c.to_string();
+            while let Some(next) =
+                chars.next_if(|next| next.is_ascii_alphanumeric() || *next == '_')
+            {
+                identifier.push(next);
+            }
+            tokens.push(identifier);
+        } else if c.is_ascii_digit() {
+            while chars
+                .next_if(|next| next.is_ascii_alphanumeric() || *next == '.')
+                .is_some()
+            {}
+        } else if c == '"' || c == '\'' {
+            let mut escaped = false;
+            for next in chars.by_ref() {
+                if next == c && !escaped {
+                    break;
+                }
+                escaped = next == '\\' && !escaped;
+            }
+        } else if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            tokens.push("::".to_string());
+        } else if !c.is_whitespace() {
+            tokens.push(c.to_string());
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(header: &str) -> Vec<(String, &'static str)> {
+        let mut keywords = Keywords::default();
+        keywords.scan_header(header);
+        keywords
+            .0
+            .into_iter()
+            .map(|(name, category)| (name, category.name()))
+            .collect()
+    }
+
+    fn keywords(expected: &[(&str, &'static str)]) -> Vec<(String, &'static str)> {
+        let mut expected: Vec<_> = expected
+            .iter()
+            .map(|(name, category)| (name.to_string(), *category))
+            .collect();
+        expected.sort();
+        expected
+    }
+
+    #[test]
+    fn typedef_struct() {
+        let header = "
+            typedef struct {
+                int x;
+                int y;
+            } Point;
+            typedef struct point3_s { int x, y, z; } Point3;
+            typedef enum { MODE_A, MODE_B } Mode;
+            typedef uint8_t pin_t;
+        ";
+        assert_eq!(
+            scan(header),
+            keywords(&[
+                ("MODE_A", "LITERAL1"),
+                ("MODE_B", "LITERAL1"),
+                ("Mode", "KEYWORD1"),
+                ("Point", "KEYWORD1"),
+                ("Point3", "KEYWORD1"),
+                ("pin_t", "KEYWORD1"),
+                ("point3_s", "KEYWORD1"),
+            ])
+        );
+    }
+
+    #[test]
+    fn classes() {
+        let header = "
+            class Servo {
+            public:
+                Servo();
+                ~Servo();
+                uint8_t attach(int pin);
+                void write(int value) { writeMicroseconds(value); }
+                Servo &operator=(const Servo &other);
+                static const int MIN_PULSE = 544;
+            private:
+                void update();
+                int pin;
+            };
+            struct Settings {
+                bool enabled() const;
+            };
+            extern Servo DefaultServo;
+        ";
+        assert_eq!(
+            scan(header),
+            keywords(&[
+                ("DefaultServo", "KEYWORD1"),
+                ("MIN_PULSE", "LITERAL1"),
+                ("Servo", "KEYWORD1"),
+                ("Settings", "KEYWORD1"),
+                ("attach", "KEYWORD2"),
+                ("enabled", "KEYWORD2"),
+                ("write", "KEYWORD2"),
+            ])
+        );
+    }
+
+    #[test]
+    fn enums() {
+        let header = "
+            enum Color { RED, GREEN = 2, BLUE };
+            enum class Direction : uint8_t { Up, Down, };
+        ";
+        assert_eq!(
+            scan(header),
+            keywords(&[
+                ("BLUE", "LITERAL1"),
+                ("Color", "KEYWORD1"),
+                ("Direction", "KEYWORD1"),
+                ("Down", "LITERAL1"),
+                ("GREEN", "LITERAL1"),
+                ("RED", "LITERAL1"),
+                ("Up", "LITERAL1"),
+            ])
+        );
+    }
+
+    #[test]
+    fn macros() {
+        let header = "
+            #ifndef LIBRARY_H
+            #define LIBRARY_H
+            #define LIBRARY_VERSION 2 // the major version
+            #define MAX(a, b) ((a) > (b) ? (a) : (b))
+            #define LONG_VALUE \\
+                (1 << 4)
+            /* #define COMMENTED_OUT 1 */
+            #endif
+        ";
+        assert_eq!(
+            scan(header),
+            keywords(&[("LIBRARY_VERSION", "LITERAL1"), ("LONG_VALUE", "LITERAL1")])
+        );
+    }
+
+    #[test]
+    fn namespaces() {
+        let header = "
+            namespace sensors {
+            class Thermometer {
+            public:
+                float read();
+            };
+            void calibrate(float offset);
+            namespace detail {
+            int _raw();
+            }
+            }
+        ";
+        assert_eq!(
+            scan(header),
+            keywords(&[
+                ("Thermometer", "KEYWORD1"),
+                ("calibrate", "KEYWORD2"),
+                ("read", "KEYWORD2"),
+            ])
+        );
+    }
+
+    #[test]
+    fn existing_keywords_win() {
+        let mut keywords = Keywords::parse("Servo\tKEYWORD2\n# comment\nattach\tKEYWORD2\n");
+        keywords.scan_header("class Servo { public: void attach(int pin); };");
+        assert_eq!(
+            keywords.render("Servo"),
+            "#######################################
+# Syntax Coloring Map For Servo
+#######################################
+
+#######################################
+# Datatypes (KEYWORD1)
+#######################################
+
+
+#######################################
+# Methods and Functions (KEYWORD2)
+#######################################
+
+Servo\tKEYWORD2
+attach\tKEYWORD2
+
+#######################################
+# Constants (LITERAL1)
+#######################################
+
+"
+        );
+    }
+}
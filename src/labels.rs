@@ -0,0 +1,256 @@
+use zed_extension_api::{
+    lsp::{Completion, CompletionKind, Symbol, SymbolKind},
+    CodeLabel, CodeLabelSpan,
+};
+
+/// Values of the constants the AVR core defines as macros, for which clangd
+/// only reports the name.
+const MACRO_VALUES: &[(&str, &str)] = &[
+    ("HIGH", "0x1"),
+    ("LOW", "0x0"),
+    ("INPUT", "0x0"),
+    ("OUTPUT", "0x1"),
+    ("INPUT_PULLUP", "0x2"),
+    ("LSBFIRST", "0"),
+    ("MSBFIRST", "1"),
+    ("CHANGE", "1"),
+    ("FALLING", "2"),
+    ("RISING", "3"),
+    ("PI", "3.1415926535897932384626433832795"),
+    ("HALF_PI", "1.5707963267948966192313216916398"),
+    ("TWO_PI", "6.283185307179586476925286766559"),
+    ("DEG_TO_RAD", "0.017453292519943295769236907684886"),
+    ("RAD_TO_DEG", "57.295779513082320876798154814105"),
+    ("EULER", "2.718281828459045235360287471352"),
+];
+
+/// Macros putting data in flash memory rather than RAM, with what they do.
+const FLASH_MACROS: &[(&str, &str)] = &[
+    ("F", "string kept in flash"),
+    ("PSTR", "string kept in flash"),
+    ("PROGMEM", "variable stored in flash"),
+];
+
+/// Renders a completion of the language server like the code it completes:
+/// functions with their return type, variables with their type, and the
+/// core's constants with their value, like `HIGH = 0x1`. The pin constants
+/// of the board show the pin they stand for, like `A0 = D14`, and what lives
+/// in or reads from flash memory is marked as such.
+///
+/// With `hide_internal`, reserved identifiers like `__builtin_expect` and
+/// `_GLIBCXX_USE_C99` get a label nothing filters on, so they drop out of the
+/// list as soon as anything is typed.
+pub fn completion(
+    completion: Completion,
+    pin_constants: &[(String, String)],
+    hide_internal: bool,
+) -> Option<CodeLabel> {
+    // clangd marks the completions that insert an `#include` with a bullet
+    let name = simplify(&strip_template_parameters(
+        completion.label.trim_start_matches(['•', ' ']),
+    ));
+    let (signature, description) = match completion.label_details {
+        Some(label_details) => (label_details.detail, label_details.description),
+        None => (None, None),
+    };
+    let signature = simplify(&signature.unwrap_or_default());
+    let detail = completion
+        .detail
+        .or(description)
+        .map(|detail| simplify(&detail))
+        .filter(|detail| !detail.is_empty());
+    let name_len = name.find('(').unwrap_or(name.len());
+
+    if hide_internal && is_internal(&name[..name_len]) {
+        return Some(CodeLabel {
+            code: String::new(),
+            spans: vec![CodeLabelSpan::literal(name, Some("comment".to_string()))],
+            filter_range: (0..0usize).into(),
+        });
+    }
+
+    if let Some((_, pin)) = pin_constants.iter().find(|(constant, _)| *constant == name) {
+        let code = format!("{name} = {pin}");
+        return Some(code_label(code, 0..name.len()));
+    }
+
+    let mut label = match (completion.kind?, detail) {
+        (CompletionKind::Function | CompletionKind::Method, Some(return_type)) => {
+            let code = format!("{return_type} {name}{signature}");
+            let start = return_type.len() + 1;
+            code_label(code, start..start + name_len)
+        }
+        (CompletionKind::Function | CompletionKind::Method | CompletionKind::Constructor, None) => {
+            let code = format!("{name}{signature}");
+            code_label(code, 0..name_len)
+        }
+        (
+            CompletionKind::Field
+            | CompletionKind::Variable
+            | CompletionKind::Property
+            | CompletionKind::Constant
+            | CompletionKind::EnumMember,
+            Some(type_name),
+        ) => {
+            let code = format!("{type_name} {name}");
+            let start = type_name.len() + 1;
+            code_label(code, start..start + name.len())
+        }
+        // Macros
+        (CompletionKind::Text | CompletionKind::Constant, None) => {
+            match MACRO_VALUES
+                .iter()
+                .find(|(macro_name, _)| *macro_name == name)
+            {
+                Some((_, value)) => code_label(format!("{name} = {value}"), 0..name.len()),
+                None if FLASH_MACROS
+                    .iter()
+                    .any(|(macro_name, _)| *macro_name == &name[..name_len]) =>
+                {
+                    code_label(name.clone(), 0..name_len)
+                }
+                None => return None,
+            }
+        }
+        (CompletionKind::Class | CompletionKind::Struct, _) => {
+            let code = format!("class {name}");
+            CodeLabel {
+                spans: vec![CodeLabelSpan::code_range(6..code.len())],
+                filter_range: (0..name.len()).into(),
+                code,
+            }
+        }
+        _ => return None,
+    };
+
+    // Reading flash memory like RAM is a classic AVR bug
+    if let Some(note) = flash_note(&name[..name_len], &label.code) {
+        label.spans.push(CodeLabelSpan::literal(
+            format!("  // {note}"),
+            Some("comment".to_string()),
+        ));
+    }
+    Some(label)
+}
+
+/// Reserved identifiers of the core and AVR headers that sketches use.
+const PUBLIC_RESERVED: &[&str] = &["_BV", "_SFR_BYTE", "_SFR_IO_ADDR", "_SFR_MEM_ADDR"];
+
+/// Whether a name is reserved to the compiler and the standard library: it
+/// contains a double underscore or starts with an underscore and a capital.
+fn is_internal(name: &str) -> bool {
+    let reserved = name.contains("__")
+        || name
+            .strip_prefix('_')
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_uppercase()));
+    reserved && !PUBLIC_RESERVED.contains(&name)
+}
+
+/// Describes how a completion relates to flash memory, if it does.
+fn flash_note(name: &str, code: &str) -> Option<&'static str> {
+    if let Some((_, note)) = FLASH_MACROS
+        .iter()
+        .find(|(macro_name, _)| *macro_name == name)
+    {
+        return Some(note);
+    }
+    let (before_name, after_name) = code.split_once(name)?;
+    let is_flash_type = |code: &str| {
+        ["__FlashStringHelper", "PGM_P", "PGM_VOID_P"]
+            .iter()
+            .any(|t| code.contains(t))
+    };
+    if is_flash_type(before_name) {
+        Some("in flash")
+    } else if is_flash_type(after_name) {
+        Some("reads from flash")
+    } else {
+        None
+    }
+}
+
+/// Renders a symbol for the project symbol search: functions with
+/// parentheses, and the interrupt vectors AVR's `ISR()` macro defines as
+/// `ISR __vector_11()`.
+pub fn symbol(symbol: Symbol) -> Option<CodeLabel> {
+    let name = simplify(&symbol.name);
+    match symbol.kind {
+        SymbolKind::Function | SymbolKind::Method | SymbolKind::Constructor => {
+            let code = format!("{name}()");
+            let mut label = code_label(code, 0..name.len());
+            if name.starts_with("__vector_") {
+                label.spans.insert(
+                    0,
+                    CodeLabelSpan::literal("ISR ", Some("keyword".to_string())),
+                );
+                label.filter_range = (4..4 + name.len()).into();
+            }
+            Some(label)
+        }
+        SymbolKind::Class | SymbolKind::Struct | SymbolKind::Enum => {
+            let keyword = match symbol.kind {
+                SymbolKind::Enum => "enum",
+                SymbolKind::Struct => "struct",
+                _ => "class",
+            };
+            let code = format!("{keyword} {name}");
+            let start = keyword.len() + 1;
+            Some(CodeLabel {
+                spans: vec![CodeLabelSpan::code_range(start..code.len())],
+                filter_range: (0..name.len()).into(),
+                code,
+            })
+        }
+        SymbolKind::Variable
+        | SymbolKind::Field
+        | SymbolKind::Constant
+        | SymbolKind::EnumMember => {
+            let len = name.len();
+            Some(code_label(name, 0..len))
+        }
+        _ => None,
+    }
+}
+
+/// Returns a label showing all of `code`, filtered on `filter_range`.
+fn code_label(code: String, filter_range: std::ops::Range<usize>) -> CodeLabel {
+    CodeLabel {
+        spans: vec![CodeLabelSpan::code_range(0..code.len())],
+        filter_range: filter_range.into(),
+        code,
+    }
+}
+
+/// Removes the template parameter list following the name of a template,
+/// like in `max<typename T>(T a, T b)`.
+fn strip_template_parameters(label: &str) -> String {
+    let Some(start) = label.find('<').filter(|&start| {
+        label[..start]
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_')
+    }) else {
+        return label.to_string();
+    };
+    let mut depth = 0;
+    for (index, c) in label[start..].char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            _ => continue,
+        }
+        if depth == 0 {
+            return format!("{}{}", &label[..start], &label[start + index + 1..]);
+        }
+    }
+    label.to_string()
+}
+
+/// Shortens the type names of the standard library and of the cores that
+/// wrap the Arduino API in a namespace.
+fn simplify(text: &str) -> String {
+    text.replace("std::__cxx11::", "std::")
+        .replace("arduino::", "")
+        .replace("basic_string<char>", "string")
+        .trim()
+        .to_string()
+}
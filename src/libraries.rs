@@ -0,0 +1,125 @@
+use crate::{cli::ArduinoCli, host};
+use zed_extension_api::{KeyValueStore, Result};
+
+/// The docs provider indexing the installed libraries, as declared in
+/// extension.toml.
+pub const DOCS_PROVIDER: &str = "arduino-libraries";
+
+/// Upper bound of indexed headers per library, as some libraries bundled with
+/// platforms ship hundreds of vendor headers.
+const MAX_INDEXED_HEADERS: usize = 64;
+
+const README_FILES: &[&str] = &[
+    "README.md",
+    "README.adoc",
+    "README.rst",
+    "README.txt",
+    "README",
+];
+
+/// A library installed in the sketchbook or bundled with an installed platform.
+pub struct Library {
+    pub name: String,
+    pub version: String,
+    /// Absolute path of the library's folder.
+    pub install_dir: String,
+}
+
+impl Library {
+    /// Reads the first of `file_names` that exists in the library's folder,
+    /// returning its name and contents.
+    pub fn read_file(&self, file_names: &[&str]) -> Option<(String, String)> {
+        file_names.iter().find_map(|file_name| {
+            let contents = host::read_text_file(&host::join(&self.install_dir, file_name)).ok()?;
+            Some((file_name.to_string(), contents))
+        })
+    }
+
+    pub fn readme(&self) -> Option<(String, String)> {
+        self.read_file(README_FILES)
+    }
+
+    /// Returns the paths of the library's header files.
+    pub fn headers(&self) -> Result<Vec<String>> {
+        host::list_files(&self.install_dir, "h")
+    }
+}
+
+/// Lists the installed libraries, including the ones bundled with platforms.
+pub fn installed(cli: &ArduinoCli) -> Result<Vec<Library>> {
+    let list = cli.run_json(&["lib", "list", "--all"])?;
+    // Older arduino-cli versions return the list itself
+    let libraries = list.get("installed_libraries").unwrap_or(&list);
+    Ok(libraries
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|entry| &entry["library"])
+        .filter_map(|library| {
+            Some(Library {
+                name: library["name"].as_str()?.to_string(),
+                version: library["version"].as_str().unwrap_or_default().to_string(),
+                install_dir: library["install_dir"].as_str()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Finds an installed library by name, ignoring case.
+pub fn find(cli: &ArduinoCli, name: &str) -> Result<Library> {
+    installed(cli)?
+        .into_iter()
+        .find(|library| library.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| format!("library `{name}` is not installed"))
+}
+
+/// Suggests the installed libraries as packages to index.
+pub fn suggest_docs_packages() -> Result<Vec<String>> {
+    let cli = ArduinoCli::for_worktree(None)?;
+    let mut names: Vec<String> = installed(&cli)?
+        .into_iter()
+        .map(|library| library.name)
+        .collect();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// Indexes the README, metadata and headers of an installed library, so the
+/// assistant sees the API of the version that is actually installed.
+pub fn index_docs(package: &str, database: &KeyValueStore) -> Result<()> {
+    let cli = ArduinoCli::for_worktree(None)?;
+    let library = find(&cli, package)?;
+
+    let mut overview = format!("# {} {}\n\n", library.name, library.version);
+    if let Some((_, readme)) = library.readme() {
+        overview.push_str(readme.trim_end());
+        overview.push_str("\n\n");
+    }
+    for file_name in ["library.properties", "keywords.txt"] {
+        if let Some((_, contents)) = library.read_file(&[file_name]) {
+            overview.push_str(&format!(
+                "## {file_name}\n\n```\n{}\n```\n\n",
+                contents.trim_end()
+            ));
+        }
+    }
+    database.insert(&library.name, &overview)?;
+
+    for header in library.headers()?.iter().take(MAX_INDEXED_HEADERS) {
+        let contents = host::read_text_file(header)?;
+        let relative_path = header
+            .strip_prefix(&library.install_dir)
+            .unwrap_or(header)
+            .trim_start_matches(['/', '\\']);
+        database.insert(
+            &format!("{}/{}", library.name, relative_path.replace('\\', "/")),
+            &format!(
+                "# {relative_path}\n\n```cpp\n{}\n```\n",
+                contents.trim_end()
+            ),
+        )?;
+    }
+
+    Ok(())
+}
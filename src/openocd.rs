@@ -0,0 +1,208 @@
+use crate::{host, toolchain};
+use std::fs;
+use zed_extension_api::{self as zed, process::Command, Result};
+
+/// Directory receiving the generated OpenOCD configurations.
+const CONFIG_DIR: &str = "openocd";
+
+/// The debug probe used for boards whose platform doesn't name one. Most
+/// hobbyist probes, like the Raspberry Pi Debug Probe and the Atmel-ICE,
+/// speak CMSIS-DAP.
+const DEFAULT_INTERFACE: &str = "interface/cmsis-dap.cfg";
+
+/// An OpenOCD executable and the directory with its scripts, if they aren't
+/// where OpenOCD looks by default.
+pub struct OpenOcd {
+    pub path: String,
+    pub scripts_dir: Option<String>,
+}
+
+/// An OpenOCD distribution published as GitHub releases.
+#[derive(Clone, Copy)]
+enum Distribution {
+    /// The xPack build of upstream OpenOCD.
+    XPack,
+    /// Espressif's fork, the only one supporting ESP32 chips.
+    Espressif,
+}
+
+impl Distribution {
+    fn for_fqbn(fqbn: &str) -> Self {
+        match fqbn.split(':').nth(1) {
+            Some("esp32") => Self::Espressif,
+            _ => Self::XPack,
+        }
+    }
+
+    fn repository(self) -> &'static str {
+        match self {
+            Self::XPack => "xpack-dev-tools/openocd-xpack",
+            Self::Espressif => "espressif/openocd-esp32",
+        }
+    }
+
+    /// Prefix of the directories the releases are extracted to.
+    fn dir_prefix(self) -> &'static str {
+        match self {
+            Self::XPack => "openocd-xpack-",
+            Self::Espressif => "openocd-esp32-",
+        }
+    }
+
+    fn asset_name(self, version: &str) -> String {
+        let (platform, arch) = zed::current_platform();
+        match self {
+            Self::XPack => {
+                let (os, extension) = match platform {
+                    zed::Os::Mac => ("darwin", "tar.gz"),
+                    zed::Os::Linux => ("linux", "tar.gz"),
+                    zed::Os::Windows => ("win32", "zip"),
+                };
+                let arch = match arch {
+                    zed::Architecture::Aarch64 => "arm64",
+                    zed::Architecture::X86 | zed::Architecture::X8664 => "x64",
+                };
+                format!("xpack-openocd-{version}-{os}-{arch}.{extension}")
+            }
+            Self::Espressif => {
+                let (target, extension) = match (platform, arch) {
+                    (zed::Os::Mac, zed::Architecture::Aarch64) => ("macos-arm64", "tar.gz"),
+                    (zed::Os::Mac, _) => ("macos", "tar.gz"),
+                    (zed::Os::Linux, zed::Architecture::Aarch64) => ("linux-arm64", "tar.gz"),
+                    (zed::Os::Linux, _) => ("linux-amd64", "tar.gz"),
+                    (zed::Os::Windows, _) => ("win64", "zip"),
+                };
+                format!("openocd-esp32-{target}-{version}.{extension}")
+            }
+        }
+    }
+
+    /// Returns the directory OpenOCD is extracted to inside `version_dir`,
+    /// and the path of its scripts inside it.
+    fn layout(self, version_dir: &str, version: &str) -> (String, &'static str) {
+        match self {
+            Self::XPack => (
+                format!("{version_dir}/xpack-openocd-{version}"),
+                "openocd/scripts",
+            ),
+            Self::Espressif => (
+                format!("{version_dir}/openocd-esp32"),
+                "share/openocd/scripts",
+            ),
+        }
+    }
+}
+
+/// Finds OpenOCD: the configured one, the one of the board's platform, the
+/// one on the `PATH`, or otherwise the latest release for the board, which is
+/// downloaded to the extension's working directory.
+pub fn locate(
+    configured: Option<String>,
+    platform: Option<&str>,
+    fqbn: &str,
+    worktree: &zed::Worktree,
+) -> Result<OpenOcd> {
+    if let Some(path) = configured {
+        return Ok(OpenOcd {
+            path,
+            scripts_dir: None,
+        });
+    }
+    let distribution = Distribution::for_fqbn(fqbn);
+    // The OpenOCD on the `PATH` is an upstream one, which can't debug ESP32 chips
+    let path_openocd = match distribution {
+        Distribution::XPack => host::which(worktree, "openocd"),
+        Distribution::Espressif => None,
+    };
+    if let Some(path) = platform
+        .map(ToString::to_string)
+        .or(path_openocd)
+        .filter(|path| runs(path))
+    {
+        return Ok(OpenOcd {
+            path,
+            scripts_dir: None,
+        });
+    }
+    download(distribution)
+}
+
+/// Returns the OpenOCD scripts selecting the probe and the chip of boards
+/// whose platform has no debug recipe.
+pub fn default_scripts(fqbn: &str) -> Option<Vec<String>> {
+    let architecture = fqbn.split(':').nth(1)?;
+    let target = match architecture {
+        "samd" => "target/at91samdXX.cfg",
+        "rp2040" | "mbed_rp2040" => "target/rp2040.cfg",
+        "nrf52" | "mbed_nano" => "target/nrf52.cfg",
+        _ => return None,
+    };
+    Some(vec![DEFAULT_INTERFACE.to_string(), target.to_string()])
+}
+
+/// Writes an OpenOCD configuration for `fqbn` sourcing `scripts`, replacing
+/// the probe with `interface` if set, and returns its absolute path.
+pub fn write_config(
+    fqbn: &str,
+    scripts: &[String],
+    interface: Option<&str>,
+    adapter_speed: Option<u32>,
+) -> Result<String> {
+    let mut config = format!("# Generated from the debug properties of {fqbn}\n");
+    if let Some(interface) = interface {
+        config.push_str(&format!("source [find {interface}]\n"));
+    }
+    for script in scripts {
+        if interface.is_some() && script.starts_with("interface/") {
+            continue;
+        }
+        config.push_str(&format!("source [find {script}]\n"));
+    }
+    if let Some(adapter_speed) = adapter_speed {
+        config.push_str(&format!("adapter speed {adapter_speed}\n"));
+    }
+
+    fs::create_dir_all(CONFIG_DIR)
+        .map_err(|e| format!("failed to create {CONFIG_DIR} directory: {e}"))?;
+    let file_name = format!("{}.cfg", fqbn.replace([':', ',', '='], "_"));
+    let path = format!("{CONFIG_DIR}/{file_name}");
+    fs::write(&path, config).map_err(|e| format!("failed to write {path}: {e}"))?;
+    host::work_dir_path(&path)
+}
+
+/// Downloads the latest release of an OpenOCD distribution, unless it
+/// already was.
+fn download(distribution: Distribution) -> Result<OpenOcd> {
+    let binary_name = match zed::current_platform().0 {
+        zed::Os::Mac | zed::Os::Linux => "openocd",
+        zed::Os::Windows => "openocd.exe",
+    };
+    let tool = toolchain::Tool {
+        name: "OpenOCD",
+        repository: distribution.repository(),
+        dir_prefix: distribution.dir_prefix(),
+    };
+    let installed = toolchain::install(
+        &tool,
+        |version| distribution.asset_name(version),
+        |installed| {
+            let (root, _) = distribution.layout(&installed.dir, &installed.version);
+            format!("{root}/bin/{binary_name}")
+        },
+    )?;
+
+    let (root, scripts_dir) = distribution.layout(&installed.dir, &installed.version);
+    Ok(OpenOcd {
+        path: host::work_dir_path(&format!("{root}/bin/{binary_name}"))?,
+        scripts_dir: Some(host::work_dir_path(&format!("{root}/{scripts_dir}"))?),
+    })
+}
+
+/// Returns whether an executable exists and runs.
+fn runs(path: &str) -> bool {
+    Command::new(path)
+        .arg("--version")
+        .output()
+        .inspect_err(|_| host::forget_tool(path))
+        .is_ok()
+}
@@ -0,0 +1,89 @@
+use crate::sketch;
+use serde::Deserialize;
+use std::sync::OnceLock;
+use zed_extension_api::{serde_json, Result};
+
+/// Pin mappings of common boards, bundled with the extension.
+const PINOUTS: &str = include_str!("../data/pinouts.json");
+
+/// The pin mapping of a board.
+#[derive(Deserialize)]
+pub struct Board {
+    /// FQBN of the board, without board options.
+    pub fqbn: String,
+    pub name: String,
+    /// The pins by function, like `("Analog", "A0–A5 (also usable as D14–D19)")`.
+    pub pins: Vec<(String, String)>,
+}
+
+/// Returns the bundled pin mapping of a board, parsed once for all callers.
+pub fn board(fqbn: &str) -> Result<Option<&'static Board>> {
+    static BOARDS: OnceLock<Result<Vec<Board>>> = OnceLock::new();
+    let boards = BOARDS
+        .get_or_init(|| {
+            serde_json::from_str(PINOUTS)
+                .map_err(|e| format!("failed to parse bundled pinouts: {e}"))
+        })
+        .as_ref()
+        .map_err(Clone::clone)?;
+    let board_id = sketch::board_id(fqbn);
+    Ok(boards.iter().find(|board| board.fqbn == board_id))
+}
+
+/// Returns the pin constants of a board the bundled pinouts name, with the
+/// pin they stand for, like `("A0", "D14")` or `("LED_BUILTIN", "D13")`.
+pub fn pin_constants(fqbn: &str) -> Vec<(String, String)> {
+    let Ok(Some(board)) = board(fqbn) else {
+        return Vec::new();
+    };
+
+    let is_constant = |name: &str| {
+        matches!(
+            name,
+            "LED_BUILTIN" | "SDA" | "SCL" | "SS" | "MOSI" | "MISO" | "SCK"
+        ) || name
+            .strip_prefix('A')
+            .is_some_and(|number| number.parse::<u32>().is_ok())
+    };
+    let mut constants = Vec::new();
+    for (function, pins) in &board.pins {
+        if function == "LED_BUILTIN" {
+            constants.push((function.clone(), pins.clone()));
+            continue;
+        }
+        for part in pins.split(", ") {
+            let (pin, note) = part.split_once(" (").unwrap_or((part, ""));
+            if let Some((name, pin)) = pin.split_once(" = ") {
+                if is_constant(name) {
+                    constants.push((name.to_string(), pin.to_string()));
+                }
+                continue;
+            }
+            // Analog pins numbered after the digital ones, like `A0–A5 (also usable as D14–D19)`
+            let Some(aliases) = note
+                .strip_prefix("also usable as ")
+                .and_then(|aliases| aliases.strip_suffix(')'))
+            else {
+                continue;
+            };
+            let range = |range: &str, prefix: char| -> Option<(u32, u32)> {
+                let (first, last) = range.split_once('–')?;
+                Some((
+                    first.strip_prefix(prefix)?.parse().ok()?,
+                    last.strip_prefix(prefix)?.parse().ok()?,
+                ))
+            };
+            if let (Some((first, last)), Some((first_digital, _))) =
+                (range(pin, 'A'), range(aliases, 'D'))
+            {
+                for number in first..=last {
+                    constants.push((
+                        format!("A{number}"),
+                        format!("D{}", first_digital + number - first),
+                    ));
+                }
+            }
+        }
+    }
+    constants
+}
@@ -0,0 +1,247 @@
+use zed_extension_api::{serde_json, Result};
+
+/// PlatformIO board IDs of common boards and their Arduino FQBNs, bundled with
+/// the extension.
+const BOARDS: &str = include_str!("../data/platformio-boards.json");
+
+/// An `[env:name]` section of a `platformio.ini`, with the values of the
+/// shared `[env]` section applied.
+pub struct Environment {
+    pub name: String,
+    pub platform: Option<String>,
+    pub board: Option<String>,
+    pub framework: Option<String>,
+    pub lib_deps: Vec<String>,
+    pub monitor_speed: Option<String>,
+    pub upload_port: Option<String>,
+}
+
+/// A parsed `platformio.ini`.
+pub struct Project {
+    pub environments: Vec<Environment>,
+    /// The environments listed in `default_envs` of the `[platformio]` section.
+    pub default_envs: Vec<String>,
+}
+
+impl Project {
+    pub fn parse(ini: &str) -> Self {
+        let sections = parse_ini(ini);
+        let section = |name: &str| {
+            sections
+                .iter()
+                .find(|(section, _)| section == name)
+                .map(|(_, values)| values.as_slice())
+                .unwrap_or_default()
+        };
+        let shared = section("env");
+
+        let environments = sections
+            .iter()
+            .filter_map(|(section, values)| {
+                let name = section.strip_prefix("env:")?;
+                let value = |key: &str| {
+                    values
+                        .iter()
+                        .chain(shared)
+                        .find(|(name, _)| name == key)
+                        .map(|(_, value)| value.clone())
+                        // Interpolations like `${env.lib_deps}` aren't resolved
+                        .filter(|value| !value.starts_with("${"))
+                };
+                Some(Environment {
+                    name: name.trim().to_string(),
+                    platform: value("platform"),
+                    board: value("board"),
+                    framework: value("framework"),
+                    lib_deps: value("lib_deps")
+                        .map(|lib_deps| list(&lib_deps))
+                        .unwrap_or_default(),
+                    monitor_speed: value("monitor_speed"),
+                    upload_port: value("upload_port"),
+                })
+            })
+            .collect();
+
+        let default_envs = section("platformio")
+            .iter()
+            .find(|(key, _)| key == "default_envs")
+            .map(|(_, value)| list(value))
+            .unwrap_or_default();
+
+        Self {
+            environments,
+            default_envs,
+        }
+    }
+
+    /// Returns the environment PlatformIO builds by default.
+    pub fn default_environment(&self) -> Option<&Environment> {
+        self.default_envs
+            .first()
+            .and_then(|name| self.environments.iter().find(|env| &env.name == name))
+            .or_else(|| self.environments.first())
+    }
+}
+
+/// Returns the Arduino FQBN of a PlatformIO board ID, if it is a known one.
+pub fn board_fqbn(board: &str) -> Result<Option<String>> {
+    let boards: serde_json::Value = serde_json::from_str(BOARDS)
+        .map_err(|e| format!("failed to parse bundled PlatformIO boards: {e}"))?;
+    Ok(boards[board].as_str().map(ToString::to_string))
+}
+
+/// Returns the Library Manager name of a `lib_deps` entry, such as
+/// `Adafruit NeoPixel` for `adafruit/Adafruit NeoPixel @ ^1.12.0`, or `None`
+/// for dependencies that aren't registry libraries (URLs and paths).
+pub fn library_name(lib_dep: &str) -> Option<&str> {
+    if lib_dep.contains("://") || lib_dep.starts_with(['/', '.', '~']) || lib_dep.ends_with(".git")
+    {
+        return None;
+    }
+    let name = lib_dep.split('@').next()?.trim();
+    let name = name.rsplit_once('/').map_or(name, |(_, name)| name).trim();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Parses the sections of an INI file into their keys and values, joining
+/// indented continuation lines into multi-line values.
+fn parse_ini(ini: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut sections: Vec<(String, Vec<(String, String)>)> = Vec::new();
+    for line in ini.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with([';', '#']) {
+            continue;
+        }
+        // Inline comments need a space before the `;`
+        let trimmed = trimmed.split(" ;").next().unwrap_or_default().trim_end();
+
+        if let Some(section) = trimmed.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            sections.push((section.trim().to_string(), Vec::new()));
+            continue;
+        }
+        let Some((_, values)) = sections.last_mut() else {
+            continue;
+        };
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = values.last_mut() {
+                value.push('\n');
+                value.push_str(trimmed);
+            }
+        } else if let Some((key, value)) = trimmed.split_once('=') {
+            values.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    sections
+}
+
+/// Splits a multi-line or comma-separated INI value into its items.
+fn list(value: &str) -> Vec<String> {
+    value
+        .split(['\n', ','])
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLATFORMIO_INI: &str = "; PlatformIO Project Configuration File
+[platformio]
+default_envs = esp32, uno
+
+[env]
+framework = arduino
+monitor_speed = 115200 ; matches Serial.begin()
+lib_deps =
+    adafruit/Adafruit NeoPixel @ ^1.12.0
+    # commented out
+    bblanchon/ArduinoJson@7.0.4
+
+[env:uno]
+platform = atmelavr
+board = uno
+upload_port = /dev/ttyACM0
+
+[env:esp32]
+platform = espressif32
+board = esp32dev
+lib_deps = ${env.lib_deps}, https://github.com/me/MyLib.git
+monitor_speed = 9600
+
+[env:custom]
+board = my_custom_board
+";
+
+    #[test]
+    fn environments() {
+        let project = Project::parse(PLATFORMIO_INI);
+        let names: Vec<&str> = project
+            .environments
+            .iter()
+            .map(|env| env.name.as_str())
+            .collect();
+        assert_eq!(names, ["uno", "esp32", "custom"]);
+
+        let uno = &project.environments[0];
+        assert_eq!(uno.platform.as_deref(), Some("atmelavr"));
+        assert_eq!(uno.board.as_deref(), Some("uno"));
+        assert_eq!(uno.upload_port.as_deref(), Some("/dev/ttyACM0"));
+        // Values of the shared `[env]` section, without the inline comment
+        assert_eq!(uno.framework.as_deref(), Some("arduino"));
+        assert_eq!(uno.monitor_speed.as_deref(), Some("115200"));
+        // Continuation lines, without the commented out one
+        assert_eq!(
+            uno.lib_deps,
+            [
+                "adafruit/Adafruit NeoPixel @ ^1.12.0",
+                "bblanchon/ArduinoJson@7.0.4"
+            ]
+        );
+
+        // The environment's own values win over the shared ones, and
+        // interpolations aren't resolved
+        let esp32 = &project.environments[1];
+        assert_eq!(esp32.monitor_speed.as_deref(), Some("9600"));
+        assert!(esp32.lib_deps.is_empty());
+    }
+
+    #[test]
+    fn default_envs() {
+        let project = Project::parse(PLATFORMIO_INI);
+        assert_eq!(project.default_envs, ["esp32", "uno"]);
+        assert_eq!(
+            project.default_environment().map(|env| env.name.as_str()),
+            Some("esp32")
+        );
+
+        let project = Project::parse("[env:nano]\nboard = nanoatmega328\n[env:uno]\nboard = uno\n");
+        assert!(project.default_envs.is_empty());
+        assert_eq!(
+            project.default_environment().map(|env| env.name.as_str()),
+            Some("nano")
+        );
+    }
+
+    #[test]
+    fn boards() {
+        assert_eq!(
+            board_fqbn("uno").unwrap().as_deref(),
+            Some("arduino:avr:uno")
+        );
+        assert_eq!(board_fqbn("my_custom_board").unwrap(), None);
+    }
+
+    #[test]
+    fn library_names() {
+        assert_eq!(
+            library_name("adafruit/Adafruit NeoPixel @ ^1.12.0"),
+            Some("Adafruit NeoPixel")
+        );
+        assert_eq!(library_name("ArduinoJson@7.0.4"), Some("ArduinoJson"));
+        assert_eq!(library_name("https://github.com/me/MyLib.git"), None);
+        assert_eq!(library_name("../lib/Local"), None);
+    }
+}
@@ -0,0 +1,95 @@
+use zed_extension_api::{
+    self as zed, serde_json, DebugAdapterBinary, Result, StartDebuggingRequestArguments,
+    StartDebuggingRequestArgumentsRequest, TcpArgumentsTemplate,
+};
+
+/// Derives the probe-rs chip name of a board from its FQBN: `RP2040` for
+/// Raspberry Pi Pico boards, or the part number in the `pnum` board option of
+/// STM32duino boards, like `STM32F103C8` for `pnum=BLUEPILL_F103C8`.
+pub fn chip(fqbn: &str) -> Option<String> {
+    let mut parts = fqbn.split(':');
+    let architecture = parts.nth(1)?;
+    match architecture {
+        "rp2040" | "mbed_rp2040" => Some("RP2040".to_string()),
+        "stm32" => {
+            let pnum = parts
+                .nth(1)?
+                .split(',')
+                .find_map(|option| option.strip_prefix("pnum="))?;
+            let part = pnum.rsplit('_').next()?;
+            part.starts_with(['C', 'F', 'G', 'H', 'L', 'U', 'W'])
+                .then(|| format!("STM32{part}"))
+        }
+        _ => None,
+    }
+}
+
+/// What a probe-rs session debugs.
+pub struct Target<'a> {
+    pub chip: &'a str,
+    pub executable: &'a str,
+    pub svd_file: Option<&'a str>,
+    /// Clock speed of the debug probe in kHz.
+    pub speed: Option<u32>,
+    /// Whether the sketch's RTT output is captured.
+    pub rtt: bool,
+}
+
+/// Returns probe-rs' own debug adapter, debugging `target`. `launch`
+/// sessions flash the executable and halt after the reset.
+pub fn binary(
+    probe_rs: String,
+    target: &Target,
+    cwd: String,
+    launch: bool,
+) -> Result<DebugAdapterBinary> {
+    let connection = zed::resolve_tcp_template(TcpArgumentsTemplate {
+        port: None,
+        host: None,
+        timeout: None,
+    })?;
+
+    let mut configuration = serde_json::json!({
+        "request": if launch { "launch" } else { "attach" },
+        "cwd": cwd,
+        "chip": target.chip,
+        "coreConfigs": [{
+            "coreIndex": 0,
+            "programBinary": target.executable,
+            "rttEnabled": target.rtt,
+        }],
+    });
+    // probe-rs shows the peripheral registers the SVD file describes
+    if let Some(svd_file) = target.svd_file {
+        configuration["coreConfigs"][0]["svdFile"] = svd_file.into();
+    }
+    if launch {
+        configuration["flashingConfig"] = serde_json::json!({
+            "flashingEnabled": true,
+            "haltAfterReset": true,
+        });
+    }
+    if let Some(speed) = target.speed {
+        configuration["speed"] = speed.into();
+    }
+
+    Ok(DebugAdapterBinary {
+        command: Some(probe_rs),
+        arguments: vec![
+            "dap-server".to_string(),
+            "--port".to_string(),
+            connection.port.to_string(),
+        ],
+        envs: Vec::new(),
+        cwd: Some(cwd),
+        connection: Some(connection),
+        request_args: StartDebuggingRequestArguments {
+            configuration: configuration.to_string(),
+            request: if launch {
+                StartDebuggingRequestArgumentsRequest::Launch
+            } else {
+                StartDebuggingRequestArgumentsRequest::Attach
+            },
+        },
+    })
+}
@@ -0,0 +1,143 @@
+use crate::cli::ArduinoCli;
+use zed_extension_api::{self as zed, process::Command};
+
+/// A USB debug probe and how to debug with it.
+pub struct Probe {
+    vendor_id: u16,
+    /// The product ID, or `None` for all the vendor's devices.
+    product_id: Option<u16>,
+    pub server: &'static str,
+    /// The OpenOCD script of the probe, or `None` for probes built into the
+    /// board, which the board's own scripts select.
+    pub interface: Option<&'static str>,
+}
+
+const PROBES: &[Probe] = &[
+    // Raspberry Pi Debug Probe
+    Probe {
+        vendor_id: 0x2e8a,
+        product_id: Some(0x000c),
+        server: "openocd",
+        interface: Some("interface/cmsis-dap.cfg"),
+    },
+    // Picoprobe
+    Probe {
+        vendor_id: 0x2e8a,
+        product_id: Some(0x0004),
+        server: "openocd",
+        interface: Some("interface/cmsis-dap.cfg"),
+    },
+    // Atmel-ICE
+    Probe {
+        vendor_id: 0x03eb,
+        product_id: Some(0x2141),
+        server: "openocd",
+        interface: Some("interface/cmsis-dap.cfg"),
+    },
+    // ST-LINK/V2
+    Probe {
+        vendor_id: 0x0483,
+        product_id: Some(0x3748),
+        server: "openocd",
+        interface: Some("interface/stlink.cfg"),
+    },
+    // ST-LINK/V2-1
+    Probe {
+        vendor_id: 0x0483,
+        product_id: Some(0x374b),
+        server: "openocd",
+        interface: Some("interface/stlink.cfg"),
+    },
+    // STLINK-V3
+    Probe {
+        vendor_id: 0x0483,
+        product_id: Some(0x374f),
+        server: "openocd",
+        interface: Some("interface/stlink.cfg"),
+    },
+    // SEGGER J-Link
+    Probe {
+        vendor_id: 0x1366,
+        product_id: None,
+        server: "jlink",
+        interface: None,
+    },
+    // USB JTAG built into the ESP32-S3, C3, C6 and H2
+    Probe {
+        vendor_id: 0x303a,
+        product_id: Some(0x1001),
+        server: "openocd",
+        interface: None,
+    },
+    // Atmel EDBG
+    Probe {
+        vendor_id: 0x03eb,
+        product_id: Some(0x2111),
+        server: "openocd",
+        interface: None,
+    },
+];
+
+/// Returns the first known debug probe attached to the computer.
+///
+/// Probes with a serial port are found through arduino-cli. On Linux, the
+/// USB devices are listed as well, to find probes without one.
+pub fn detect(cli: &ArduinoCli) -> Option<&'static Probe> {
+    let mut devices = serial_devices(cli);
+    if let zed::Os::Linux = zed::current_platform().0 {
+        devices.extend(usb_devices());
+    }
+    PROBES.iter().find(|probe| {
+        devices.iter().any(|&(vendor_id, product_id)| {
+            vendor_id == probe.vendor_id && probe.product_id.is_none_or(|id| id == product_id)
+        })
+    })
+}
+
+/// Returns the USB vendor and product IDs of the serial ports.
+fn serial_devices(cli: &ArduinoCli) -> Vec<(u16, u16)> {
+    let Ok(detected) = cli.run_json(&["board", "list"]) else {
+        return Vec::new();
+    };
+    // Newer arduino-cli versions wrap the list in a `detected_ports` object
+    let detected_ports = detected.get("detected_ports").unwrap_or(&detected);
+    detected_ports
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|detected_port| {
+            let properties = &detected_port["port"]["properties"];
+            Some((
+                parse_id(properties["vid"].as_str()?)?,
+                parse_id(properties["pid"].as_str()?)?,
+            ))
+        })
+        .collect()
+}
+
+/// Returns the USB vendor and product IDs of all USB devices, on Linux.
+fn usb_devices() -> Vec<(u16, u16)> {
+    const SCRIPT: &str = r#"for device in /sys/bus/usb/devices/*; do
+  [ -f "$device/idVendor" ] && echo "$(cat "$device/idVendor") $(cat "$device/idProduct")"
+done"#;
+    let Ok(output) = Command::new("sh").args(["-c", SCRIPT]).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (vendor_id, product_id) = line.split_once(' ')?;
+            Some((parse_id(vendor_id)?, parse_id(product_id)?))
+        })
+        .collect()
+}
+
+/// Parses a hexadecimal USB ID, like `0x2E8A` or `2e8a`.
+fn parse_id(id: &str) -> Option<u16> {
+    let id = id.trim();
+    let id = id
+        .strip_prefix("0x")
+        .or_else(|| id.strip_prefix("0X"))
+        .unwrap_or(id);
+    u16::from_str_radix(id, 16).ok()
+}
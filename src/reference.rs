@@ -0,0 +1,131 @@
+use std::fs;
+use zed_extension_api::{
+    http_client::{HttpMethod, HttpRequest, RedirectPolicy},
+    serde_json, Result,
+};
+
+/// GitHub repository holding the sources of the Arduino language reference.
+const REFERENCE_REPOSITORY: &str = "arduino/reference-en";
+const REFERENCE_BRANCH: &str = "master";
+
+/// File in the extension's working directory caching the paths of all
+/// reference entries, so the GitHub API is only queried when an entry is
+/// missing from it.
+const INDEX_CACHE: &str = "reference-index.json";
+
+/// An entry of the Arduino language reference.
+pub struct ReferenceEntry {
+    /// Path of the entry in the reference repository.
+    pub path: String,
+    /// The AsciiDoc source of the entry.
+    pub content: String,
+}
+
+impl ReferenceEntry {
+    /// Returns the URL of the entry on GitHub.
+    pub fn url(&self) -> String {
+        format!(
+            "https://github.com/{REFERENCE_REPOSITORY}/blob/{REFERENCE_BRANCH}/{}",
+            encode_path(&self.path)
+        )
+    }
+
+    /// Returns the URL of the entry on the official Arduino documentation
+    /// site, which adds the examples and related entries.
+    pub fn docs_url(&self) -> String {
+        // `Language/Functions/Digital IO/digitalWrite.adoc` is published as
+        // `language-reference/en/functions/digital-io/digitalwrite/`
+        let path = self
+            .path
+            .trim_start_matches("Language/")
+            .trim_end_matches(".adoc")
+            .to_lowercase()
+            .replace(' ', "-");
+        format!("https://docs.arduino.cc/language-reference/en/{path}/")
+    }
+}
+
+/// Looks up the reference entry for `name`, either a function or class name
+/// (`digitalWrite`) or a member of a class (`Wire.begin`).
+pub fn lookup(name: &str) -> Result<ReferenceEntry> {
+    let name = name.trim().trim_end_matches("()");
+    let suffix = format!("/{}.adoc", name.replace('.', "/")).to_lowercase();
+    let find = |index: &[String]| {
+        index
+            .iter()
+            .find(|path| path.to_lowercase().ends_with(&suffix))
+            .cloned()
+    };
+
+    // Refresh the cached index when it doesn't know the entry, as it may
+    // have been added to the reference since
+    let path = match cached_index().as_deref().and_then(find) {
+        Some(path) => path,
+        None => find(&fetch_index()?)
+            .ok_or_else(|| format!("no Arduino reference entry found for `{name}`"))?,
+    };
+
+    let content = fetch(&format!(
+        "https://raw.githubusercontent.com/{REFERENCE_REPOSITORY}/{REFERENCE_BRANCH}/{}",
+        encode_path(&path)
+    ))?;
+
+    Ok(ReferenceEntry { path, content })
+}
+
+fn cached_index() -> Option<Vec<String>> {
+    let index = fs::read_to_string(INDEX_CACHE).ok()?;
+    serde_json::from_str(&index).ok()
+}
+
+fn fetch_index() -> Result<Vec<String>> {
+    let tree = fetch(&format!(
+        "https://api.github.com/repos/{REFERENCE_REPOSITORY}/git/trees/{REFERENCE_BRANCH}?recursive=1"
+    ))?;
+    let tree: serde_json::Value =
+        serde_json::from_str(&tree).map_err(|e| format!("failed to parse reference index: {e}"))?;
+
+    let index: Vec<String> = tree["tree"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry["path"].as_str())
+        .filter(|path| path.starts_with("Language/") && path.ends_with(".adoc"))
+        .map(ToString::to_string)
+        .collect();
+
+    // Failing to cache the index only costs another request next time
+    if let Ok(serialized) = serde_json::to_string(&index) {
+        fs::write(INDEX_CACHE, serialized).ok();
+    }
+
+    Ok(index)
+}
+
+fn fetch(url: &str) -> Result<String> {
+    let response = HttpRequest::builder()
+        .method(HttpMethod::Get)
+        .url(url)
+        .header("User-Agent", "zed-arduino")
+        .redirect_policy(RedirectPolicy::FollowAll)
+        .build()?
+        .fetch()
+        .map_err(|e| format!("failed to fetch {url}: {e}"))?;
+
+    String::from_utf8(response.body).map_err(|e| format!("invalid response from {url}: {e}"))
+}
+
+/// Percent-encodes the characters of a repository path that aren't allowed
+/// in URLs, such as the spaces in `Language/Functions/Digital IO`.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
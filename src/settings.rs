@@ -0,0 +1,638 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use zed_extension_api::{
+    self as zed, serde_json,
+    settings::{CommandSettings, LspSettings},
+};
+
+/// Settings that end up on the language server's command line or in its
+/// environment. They only take effect when the language server is
+/// (re)started, while everything else is pushed to the running server as
+/// workspace configuration.
+const COMMAND_LINE_KEYS: &[&str] = &[
+    "cli_path",
+    "clangd_path",
+    "cli_config",
+    "fqbn",
+    "cli",
+    "directories",
+    "auto_detect_tools",
+    "lsp",
+    "format",
+    "real_time_diagnostics",
+    "skip_library_discovery_on_rebuild",
+    "extra_args",
+];
+
+/// Settings only the extension itself reads, for the slash commands, tasks
+/// and new sketches. They are neither passed to the language server nor
+/// need a restart.
+const EXTENSION_KEYS: &[&str] = &[
+    "port",
+    "monitor",
+    "sketch_template",
+    "windows",
+    "macos",
+    "linux",
+];
+
+/// Structured settings read from the `settings` object of the `arduino` LSP
+/// settings block.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ArduinoSettings {
+    /// Path to the `arduino-cli` executable.
+    pub cli_path: Option<String>,
+    /// Path to the `clangd` executable.
+    pub clangd_path: Option<String>,
+    /// The `arduino-cli.yaml` configuration file to use.
+    pub cli_config: CliConfig,
+    /// Fully qualified board name of the target board.
+    pub fqbn: Option<String>,
+    /// Serial port the board is attached to. The language server has no use
+    /// for it; it is read by the commands that talk to the board.
+    pub port: Option<String>,
+    pub monitor: MonitorOptions,
+    pub cli: CliOptions,
+    pub directories: Directories,
+    /// Whether `clangd` and `arduino-cli` are looked up on the `PATH` when
+    /// no explicit path is configured.
+    pub auto_detect_tools: bool,
+    pub lsp: LspOptions,
+    pub format: FormatOptions,
+    /// Whether the language server rebuilds the sketch on every change to
+    /// publish diagnostics.
+    pub real_time_diagnostics: bool,
+    /// Whether library discovery is skipped when the sketch is rebuilt.
+    pub skip_library_discovery_on_rebuild: bool,
+    /// Arguments appended after all the flags computed by the extension.
+    pub extra_args: Vec<String>,
+    /// The `.ino` file `/arduino-new` creates, with `{name}`, `{fqbn}` and
+    /// `{board}` placeholders.
+    pub sketch_template: Option<String>,
+}
+
+impl Default for ArduinoSettings {
+    fn default() -> Self {
+        Self {
+            cli_path: None,
+            clangd_path: None,
+            cli_config: CliConfig::Auto,
+            fqbn: None,
+            port: None,
+            monitor: MonitorOptions::default(),
+            cli: CliOptions::default(),
+            directories: Directories::default(),
+            auto_detect_tools: true,
+            lsp: LspOptions::default(),
+            format: FormatOptions::default(),
+            real_time_diagnostics: true,
+            skip_library_discovery_on_rebuild: false,
+            extra_args: Vec::new(),
+            sketch_template: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LspOptions {
+    /// How much the language server should log.
+    pub verbosity: Verbosity,
+    /// Whether clangd adds the `#include` of a completed symbol declared in a
+    /// header that isn't included yet.
+    pub insert_includes: bool,
+    /// Whether completions of compiler and library internals, like
+    /// `__builtin_*`, are hidden once something is typed.
+    pub hide_internal_completions: bool,
+}
+
+impl Default for LspOptions {
+    fn default() -> Self {
+        Self {
+            verbosity: Verbosity::default(),
+            insert_includes: true,
+            hide_internal_completions: true,
+        }
+    }
+}
+
+impl LspOptions {
+    /// Returns the flags for clangd, passed through the `CLANGD_FLAGS`
+    /// environment variable since the language server spawns clangd itself.
+    /// `query_drivers` are globs of the compilers clangd may run to learn
+    /// their system include paths.
+    pub fn clangd_flags(&self, query_drivers: &[String]) -> String {
+        let header_insertion = if self.insert_includes {
+            "--header-insertion=iwyu"
+        } else {
+            "--header-insertion=never"
+        };
+        // The index of the sources in the compilation database is what finds the
+        // definitions in the core and the libraries, rather than only their headers
+        let mut flags = vec![
+            header_insertion.to_string(),
+            "--background-index".to_string(),
+        ];
+        if !query_drivers.is_empty() {
+            flags.push(format!("\"--query-driver={}\"", query_drivers.join(",")));
+        }
+        flags.extend(self.verbosity.clangd_flags().map(ToString::to_string));
+        flags.join(" ")
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    #[default]
+    Off,
+    Info,
+    Debug,
+    Trace,
+}
+
+/// How the language server's `-cli-config` flag is chosen.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum CliConfig {
+    /// Use the configuration file in the Arduino data directory, if any.
+    #[default]
+    Auto,
+    /// Don't pass `-cli-config`, leaving discovery to arduino-cli.
+    Disabled,
+    /// Use the configuration file at this path.
+    Path(String),
+}
+
+impl<'de> Deserialize<'de> for CliConfig {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Enabled(bool),
+            Path(String),
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Enabled(true) => CliConfig::Auto,
+            Raw::Enabled(false) => CliConfig::Disabled,
+            Raw::Path(path) if path == "none" => CliConfig::Disabled,
+            Raw::Path(path) => CliConfig::Path(path),
+        })
+    }
+}
+
+/// How the serial port of the board is opened by `arduino-cli monitor`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonitorOptions {
+    /// Baud rate of the serial connection.
+    pub baud: Option<u32>,
+    /// Appended to every line sent to the board.
+    pub line_ending: LineEnding,
+    /// Whether the DTR and RTS lines are asserted. Many boards reset when the
+    /// port is opened with DTR on.
+    pub dtr: Option<bool>,
+    pub rts: Option<bool>,
+}
+
+impl MonitorOptions {
+    /// Returns the port settings to pass to `arduino-cli monitor --config`.
+    pub fn port_config(&self) -> Vec<String> {
+        let on_off = |enabled: bool| if enabled { "on" } else { "off" };
+        let mut config = Vec::new();
+        if let Some(baud) = self.baud {
+            config.push(format!("baudrate={baud}"));
+        }
+        if let Some(dtr) = self.dtr {
+            config.push(format!("dtr={}", on_off(dtr)));
+        }
+        if let Some(rts) = self.rts {
+            config.push(format!("rts={}", on_off(rts)));
+        }
+        config
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    None,
+    Nl,
+    Cr,
+    Crlf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::None => "",
+            LineEnding::Nl => "\n",
+            LineEnding::Cr => "\r",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CliOptions {
+    /// Address (`host:port`) of a running `arduino-cli daemon` to use instead
+    /// of spawning `arduino-cli` for every request.
+    pub daemon_address: Option<String>,
+    /// Instance number to use on that daemon.
+    pub daemon_instance: Option<u32>,
+}
+
+/// Overrides for arduino-cli's directories, exported to the language server
+/// as the matching `ARDUINO_DIRECTORIES_*` variables.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Directories {
+    /// Where cores and tools are installed (`Arduino15`).
+    pub data: Option<String>,
+    /// The sketchbook, holding user-installed libraries.
+    pub sketchbook: Option<String>,
+    /// Where downloaded archives are cached.
+    pub downloads: Option<String>,
+}
+
+impl Directories {
+    /// Returns the environment variables arduino-cli reads these directories
+    /// from.
+    pub fn env_vars(&self) -> Vec<(&'static str, String)> {
+        [
+            ("ARDUINO_DIRECTORIES_DATA", &self.data),
+            ("ARDUINO_DIRECTORIES_USER", &self.sketchbook),
+            ("ARDUINO_DIRECTORIES_DOWNLOADS", &self.downloads),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value.clone()?)))
+        .collect()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FormatOptions {
+    /// The clang-format style used when formatting sketches.
+    pub style: FormatStyle,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum FormatStyle {
+    /// The language server's built-in style, matching the Arduino IDE.
+    #[default]
+    Arduino,
+    Google,
+    #[serde(rename = "LLVM")]
+    Llvm,
+    /// The `.clang-format` file found in the sketch.
+    #[serde(rename = "file")]
+    File,
+}
+
+/// The `arduino` LSP settings of a worktree, read and parsed once for
+/// everything computing the language server's command or configuration.
+pub struct ResolvedSettings {
+    /// The `binary` block: the path, arguments and environment of the
+    /// language server.
+    pub binary: Option<CommandSettings>,
+    pub arduino: ArduinoSettings,
+    /// The part of `settings` that ends up on the command line.
+    pub command_line: serde_json::Value,
+    /// The part of `settings` forwarded to the running language server.
+    pub runtime: serde_json::Value,
+}
+
+impl ResolvedSettings {
+    /// Reads the settings that apply to `worktree`, failing when the
+    /// extension's own settings are malformed.
+    pub fn for_worktree(worktree: &zed::Worktree) -> Result<Self, String> {
+        let (binary, settings) = match LspSettings::for_worktree("arduino", worktree) {
+            Ok(lsp_settings) => (lsp_settings.binary, lsp_settings.settings),
+            Err(_) => (None, None),
+        };
+        let settings = for_current_platform(settings.as_ref())?;
+        let arduino = ArduinoSettings::from_json(settings.as_ref())?;
+        let (command_line, runtime) = split_command_line_settings(settings.as_ref());
+        Ok(Self {
+            binary,
+            arduino,
+            command_line,
+            runtime,
+        })
+    }
+
+    /// Returns the part of the settings that determines the language
+    /// server's command line, to tell when a change needs a restart.
+    pub fn command_line_fingerprint(&self) -> serde_json::Value {
+        serde_json::json!({
+            "binary": self.binary,
+            "settings": self.command_line,
+        })
+    }
+
+    /// Returns the settings that changed since the language server was
+    /// started with the settings of `fingerprint`, named like `binary` or
+    /// `settings.fqbn`.
+    pub fn changed_since(&self, fingerprint: &serde_json::Value) -> Vec<String> {
+        let current = self.command_line_fingerprint();
+        let mut changed = Vec::new();
+        if current["binary"] != fingerprint["binary"] {
+            changed.push("binary".to_string());
+        }
+        let keys = |fingerprint: &serde_json::Value| -> Vec<String> {
+            fingerprint["settings"]
+                .as_object()
+                .map(|settings| settings.keys().cloned().collect())
+                .unwrap_or_default()
+        };
+        let mut keys = [keys(&current), keys(fingerprint)].concat();
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            if current["settings"][&key] != fingerprint["settings"][&key] {
+                changed.push(format!("settings.{key}"));
+            }
+        }
+        changed
+    }
+
+    /// Returns the environment for the language server and the arduino-cli
+    /// processes of the worktree: `shell_env` with the variables of
+    /// `binary.env` on top, and the configured directories unless they were
+    /// set explicitly.
+    pub fn env(&self, shell_env: Vec<(String, String)>) -> HashMap<String, String> {
+        let mut env: HashMap<String, String> = shell_env.into_iter().collect();
+        if let Some(binary_env) = self.binary.as_ref().and_then(|binary| binary.env.clone()) {
+            env.extend(binary_env);
+        }
+        for (name, value) in self.arduino.directories.env_vars() {
+            env.entry(name.to_string()).or_insert(value);
+        }
+        env
+    }
+}
+
+impl ArduinoSettings {
+    /// Parses the settings object, with the platform overrides already
+    /// merged, naming the offending key in the error when a value has the
+    /// wrong shape.
+    fn from_json(settings: Option<&serde_json::Value>) -> Result<Self, String> {
+        let Some(settings) = settings else {
+            return Ok(Self::default());
+        };
+
+        for key in settings
+            .as_object()
+            .into_iter()
+            .flat_map(|settings| settings.keys())
+        {
+            if let Some(suggestion) = misspelled_key(key) {
+                eprintln!("arduino: unknown setting `{key}`, did you mean `{suggestion}`?");
+            }
+        }
+
+        let settings: Self = serde_path_to_error::deserialize(settings).map_err(|err| {
+            format!(
+                "invalid value for `settings.{}`: {}",
+                err.path(),
+                err.inner()
+            )
+        })?;
+
+        // The language server refuses to start with only one of the daemon flags
+        if settings.cli.daemon_address.is_some() != settings.cli.daemon_instance.is_some() {
+            return Err(
+                "`settings.cli.daemon_address` and `settings.cli.daemon_instance` must be set together"
+                    .to_string(),
+            );
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Checks the shape of the settings object and merges the sub-object for the
+/// current platform over it. Returns `None` without settings.
+fn for_current_platform(
+    settings: Option<&serde_json::Value>,
+) -> Result<Option<serde_json::Value>, String> {
+    let Some(settings) = settings.filter(|settings| !settings.is_null()) else {
+        return Ok(None);
+    };
+    if !settings.is_object() {
+        return Err("`settings` must be an object".to_string());
+    }
+    for os_key in PLATFORM_KEYS {
+        if settings.get(os_key).is_some_and(|value| !value.is_object()) {
+            return Err(format!("`settings.{os_key}` must be an object"));
+        }
+    }
+    Ok(Some(with_platform_overrides(settings)))
+}
+
+/// Keys of the sub-objects overriding settings on a single platform.
+const PLATFORM_KEYS: [&str; 3] = ["windows", "macos", "linux"];
+
+/// Returns the settings with the sub-object for the current platform merged
+/// over them and all platform sub-objects removed.
+fn with_platform_overrides(settings: &serde_json::Value) -> serde_json::Value {
+    let mut settings = settings.clone();
+    let Some(object) = settings.as_object_mut() else {
+        return settings;
+    };
+
+    let current = match zed::current_platform().0 {
+        zed::Os::Windows => "windows",
+        zed::Os::Mac => "macos",
+        zed::Os::Linux => "linux",
+    };
+    let mut overrides = None;
+    for os_key in PLATFORM_KEYS {
+        let value = object.remove(os_key);
+        if os_key == current {
+            overrides = value;
+        }
+    }
+
+    if let Some(overrides) = overrides {
+        merge_json(&mut settings, overrides);
+    }
+    settings
+}
+
+/// Merges `overrides` into `base`, recursing into objects and replacing every
+/// other value.
+fn merge_json(base: &mut serde_json::Value, overrides: serde_json::Value) {
+    match (base, overrides) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+/// Returns the known setting `key` is most likely a typo of. Unknown keys are
+/// otherwise valid, as they're forwarded to the language server.
+fn misspelled_key(key: &str) -> Option<&'static str> {
+    if COMMAND_LINE_KEYS.contains(&key) || EXTENSION_KEYS.contains(&key) {
+        return None;
+    }
+
+    COMMAND_LINE_KEYS
+        .iter()
+        .chain(EXTENSION_KEYS)
+        .copied()
+        .find(|known| edit_distance(key, known) <= 2)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Splits the settings object into the part that determines the language
+/// server's command line and the part that is forwarded to the running
+/// server: the keys the extension doesn't know. Settings only the extension
+/// reads end up in neither.
+fn split_command_line_settings(
+    settings: Option<&serde_json::Value>,
+) -> (serde_json::Value, serde_json::Value) {
+    let mut command_line = serde_json::Map::new();
+    let mut runtime = serde_json::Map::new();
+
+    if let Some(serde_json::Value::Object(settings)) = settings {
+        for (key, value) in settings {
+            if COMMAND_LINE_KEYS.contains(&key.as_str()) {
+                command_line.insert(key.clone(), value.clone());
+            } else if !EXTENSION_KEYS.contains(&key.as_str()) {
+                runtime.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    (command_line.into(), runtime.into())
+}
+
+impl FormatStyle {
+    /// Returns the clang-format configuration for this style, or `None` when
+    /// the language server's own lookup already produces it.
+    pub fn clang_format_config(self) -> Option<&'static str> {
+        match self {
+            FormatStyle::Arduino | FormatStyle::File => None,
+            FormatStyle::Google => Some("BasedOnStyle: Google\n"),
+            FormatStyle::Llvm => Some("BasedOnStyle: LLVM\n"),
+        }
+    }
+}
+
+impl Verbosity {
+    /// Returns the language server flags that enable logging at this level.
+    ///
+    /// The language server only has a single logging switch, so every level
+    /// other than `off` enables it and points it at `log_dir`.
+    pub fn language_server_args(self, log_dir: &str) -> Vec<String> {
+        match self {
+            Verbosity::Off => Vec::new(),
+            Verbosity::Info | Verbosity::Debug | Verbosity::Trace => vec![
+                "-log".to_string(),
+                "-logpath".to_string(),
+                log_dir.to_string(),
+            ],
+        }
+    }
+
+    /// Returns the extra flags for clangd at this level.
+    fn clangd_flags(self) -> Option<&'static str> {
+        match self {
+            Verbosity::Off | Verbosity::Info => None,
+            Verbosity::Debug => Some("--log=verbose"),
+            Verbosity::Trace => Some("--log=verbose --pretty"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed_extension_api::serde_json::json;
+
+    fn resolved(settings: serde_json::Value) -> ResolvedSettings {
+        let (command_line, runtime) = split_command_line_settings(Some(&settings));
+        ResolvedSettings {
+            binary: None,
+            arduino: ArduinoSettings::default(),
+            command_line,
+            runtime,
+        }
+    }
+
+    #[test]
+    fn split_settings() {
+        let settings = resolved(json!({
+            "fqbn": "arduino:avr:uno",
+            "lsp": { "verbosity": "info" },
+            "port": "/dev/ttyACM0",
+            "monitor": { "baudrate": 115200 },
+            "sketch_template": "template.ino",
+            "customServerSetting": true,
+        }));
+        assert_eq!(
+            settings.command_line,
+            json!({ "fqbn": "arduino:avr:uno", "lsp": { "verbosity": "info" } })
+        );
+        assert_eq!(settings.runtime, json!({ "customServerSetting": true }));
+    }
+
+    #[test]
+    fn changed_settings() {
+        let started_with = resolved(json!({
+            "fqbn": "arduino:avr:uno",
+            "real_time_diagnostics": false,
+            "port": "/dev/ttyACM0",
+        }))
+        .command_line_fingerprint();
+
+        let unchanged = resolved(json!({
+            "fqbn": "arduino:avr:uno",
+            "real_time_diagnostics": false,
+            "port": "/dev/ttyUSB0",
+            "customServerSetting": true,
+        }));
+        assert!(unchanged.changed_since(&started_with).is_empty());
+
+        let changed = resolved(json!({
+            "fqbn": "arduino:avr:nano",
+            "extra_args": ["-jobs", "2"],
+        }));
+        assert_eq!(
+            changed.changed_since(&started_with),
+            [
+                "settings.extra_args",
+                "settings.fqbn",
+                "settings.real_time_diagnostics",
+            ]
+        );
+    }
+}
@@ -0,0 +1,313 @@
+use crate::{host, settings::ArduinoSettings};
+use zed_extension_api::{self as zed, serde_json};
+
+/// Returns the FQBN of the board the worktree's sketch targets: the one from
+/// the settings, or the `default_fqbn` of the sketch's `sketch.yaml`.
+pub fn active_fqbn(worktree: &zed::Worktree, settings: &ArduinoSettings) -> Option<String> {
+    settings
+        .fqbn
+        .clone()
+        .or_else(|| sketch_yaml_value(worktree, "default_fqbn"))
+}
+
+/// Reads a top-level value of the `sketch.yaml` at the root of the worktree.
+pub fn sketch_yaml_value(worktree: &zed::Worktree, key: &str) -> Option<String> {
+    top_level_value(&worktree.read_text_file("sketch.yaml").ok()?, key)
+}
+
+/// Reads a top-level value of a `sketch.yaml`.
+fn top_level_value(sketch_yaml: &str, key: &str) -> Option<String> {
+    sketch_yaml.lines().find_map(|line| {
+        let value = strip_comment(line).strip_prefix(key)?.strip_prefix(':')?;
+        let value = value.trim().trim_matches(['"', '\'']);
+        (!value.is_empty()).then(|| value.to_string())
+    })
+}
+
+/// Returns a profile of the `sketch.yaml` at the root of the worktree: the
+/// named one, or otherwise the `default_profile`.
+pub fn profile(
+    worktree: &zed::Worktree,
+    name: Option<&str>,
+) -> Option<serde_json::Map<String, serde_json::Value>> {
+    profile_of(&worktree.read_text_file("sketch.yaml").ok()?, name)
+}
+
+/// Returns a profile of a `sketch.yaml`: the named one, or otherwise the
+/// `default_profile`.
+fn profile_of(
+    sketch_yaml: &str,
+    name: Option<&str>,
+) -> Option<serde_json::Map<String, serde_json::Value>> {
+    let name = name
+        .map(ToString::to_string)
+        .or_else(|| top_level_value(sketch_yaml, "default_profile"))?;
+    let mut lines: Vec<(usize, String)> = sketch_yaml
+        .lines()
+        .filter_map(|line| {
+            let line = strip_comment(line);
+            let content = line.trim();
+            (!content.is_empty())
+                .then(|| (line.len() - line.trim_start().len(), content.to_string()))
+        })
+        .collect();
+    let serde_json::Value::Object(mut sketch) = parse_yaml(&mut lines, &mut 0, 0) else {
+        return None;
+    };
+    match sketch.get_mut("profiles")?.get_mut(&name)?.take() {
+        serde_json::Value::Object(profile) => Some(profile),
+        _ => None,
+    }
+}
+
+/// Removes the comment from a YAML line. A `#` starts a comment at the start
+/// of the line or after whitespace, unless it is inside quotes.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if previous.is_whitespace() && c == '#' => return line[..index].trim_end(),
+            None if previous.is_whitespace() && (c == '"' || c == '\'') => quote = Some(c),
+            None => {}
+        }
+        previous = c;
+    }
+    line
+}
+
+/// Parses the YAML block starting at `lines[*index]`, if it is indented by
+/// at least `indent`. Only the mappings, sequences and plain values
+/// `sketch.yaml` files are made of are supported.
+fn parse_yaml(
+    lines: &mut [(usize, String)],
+    index: &mut usize,
+    indent: usize,
+) -> serde_json::Value {
+    let Some((block_indent, first)) = lines.get(*index).cloned() else {
+        return serde_json::Value::Null;
+    };
+    if block_indent < indent {
+        return serde_json::Value::Null;
+    }
+
+    if is_sequence_item(&first) {
+        let mut items = Vec::new();
+        while let Some((line_indent, line)) = lines.get(*index).cloned() {
+            if line_indent != block_indent || !is_sequence_item(&line) {
+                break;
+            }
+            let item = line[1..].trim_start();
+            if item.is_empty() {
+                *index += 1;
+                items.push(parse_yaml(lines, index, block_indent + 1));
+            } else if mapping_entry(item).is_some() {
+                // Parse `- key: value` as a mapping indented like its first key
+                let item_indent = block_indent + line.len() - item.len();
+                lines[*index] = (item_indent, item.to_string());
+                items.push(parse_yaml(lines, index, item_indent));
+            } else {
+                *index += 1;
+                items.push(yaml_scalar(item));
+            }
+        }
+        return serde_json::Value::Array(items);
+    }
+
+    let mut mapping = serde_json::Map::new();
+    while let Some((line_indent, line)) = lines.get(*index).cloned() {
+        let Some((key, value)) = mapping_entry(&line).filter(|_| line_indent == block_indent)
+        else {
+            break;
+        };
+        *index += 1;
+        let value = if !value.is_empty() {
+            yaml_scalar(value)
+        } else if lines.get(*index).is_some_and(|(next_indent, next)| {
+            *next_indent == block_indent && is_sequence_item(next)
+        }) {
+            // Sequences may be indented like the key they belong to
+            parse_yaml(lines, index, block_indent)
+        } else {
+            parse_yaml(lines, index, block_indent + 1)
+        };
+        mapping.insert(key.to_string(), value);
+    }
+    serde_json::Value::Object(mapping)
+}
+
+fn is_sequence_item(line: &str) -> bool {
+    line == "-" || line.starts_with("- ")
+}
+
+/// Splits a `key: value` line, with an empty value for nested blocks.
+fn mapping_entry(line: &str) -> Option<(&str, &str)> {
+    match line.split_once(": ") {
+        Some((key, value)) => Some((key.trim(), value.trim())),
+        None => line.strip_suffix(':').map(|key| (key.trim(), "")),
+    }
+}
+
+fn yaml_scalar(value: &str) -> serde_json::Value {
+    if let Some(quoted) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        })
+    {
+        return quoted.into();
+    }
+    match value {
+        "true" => true.into(),
+        "false" => false.into(),
+        _ => value
+            .parse::<u64>()
+            .map_or_else(|_| value.into(), serde_json::Value::from),
+    }
+}
+
+/// Strips the board options from an FQBN, leaving `vendor:architecture:board`.
+pub fn board_id(fqbn: &str) -> &str {
+    match fqbn.match_indices(':').nth(2) {
+        Some((index, _)) => &fqbn[..index],
+        None => fqbn,
+    }
+}
+
+/// Returns the `.ino` files of the sketch at the root of the worktree in the
+/// order the builder concatenates them, see [`tab_order`].
+pub fn tabs(worktree: &zed::Worktree) -> Result<(Option<String>, Vec<String>), String> {
+    let root = worktree.root_path();
+    let tabs = host::list_files(&root, "ino")?
+        .iter()
+        .filter(|path| {
+            path.strip_suffix(host::file_name(path))
+                .is_some_and(|parent| parent.trim_end_matches(['/', '\\']) == root)
+        })
+        .map(|path| host::file_name(path).to_string())
+        .collect();
+    Ok(tab_order(host::file_name(&root), tabs))
+}
+
+/// Orders the `.ino` files of the sketch in `folder` like the builder
+/// concatenates them: the main file named after the folder first, then the
+/// other tabs alphabetically. The main file is `None` when the folder has no
+/// `.ino` named after it.
+fn tab_order(folder: &str, mut tabs: Vec<String>) -> (Option<String>, Vec<String>) {
+    let main = format!("{folder}.ino");
+    tabs.sort();
+    let main = tabs
+        .iter()
+        .position(|tab| *tab == main)
+        .map(|index| tabs.remove(index));
+    (main, tabs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zed_extension_api::serde_json::json;
+
+    const SKETCH_YAML: &str = r#"# Profiles of the weather station
+profiles:
+  uno:
+    fqbn: arduino:avr:uno # the classic one
+    programmer: "atmel_ice"
+    notes: 'built on #3'
+    platforms:
+      - platform: arduino:avr (1.8.6)
+      - platform: esp32:esp32 (3.0.0)
+        platform_index_url: https://espressif.github.io/arduino-esp32/package_esp32_index.json
+    libraries:
+    - Servo (1.2.1)
+    - "Adafruit GFX Library (1.11.9)"
+    debug:
+      adapter_speed: 4000
+      reset: true
+
+  nano:
+    fqbn: arduino:avr:nano
+
+default_profile: uno
+"#;
+
+    fn profile(sketch_yaml: &str, name: Option<&str>) -> Option<serde_json::Value> {
+        profile_of(sketch_yaml, name).map(serde_json::Value::Object)
+    }
+
+    #[test]
+    fn default_profile() {
+        assert_eq!(
+            profile(SKETCH_YAML, None),
+            Some(json!({
+                "fqbn": "arduino:avr:uno",
+                "programmer": "atmel_ice",
+                "notes": "built on #3",
+                "platforms": [
+                    { "platform": "arduino:avr (1.8.6)" },
+                    {
+                        "platform": "esp32:esp32 (3.0.0)",
+                        "platform_index_url": "https://espressif.github.io/arduino-esp32/package_esp32_index.json",
+                    },
+                ],
+                "libraries": ["Servo (1.2.1)", "Adafruit GFX Library (1.11.9)"],
+                "debug": { "adapter_speed": 4000, "reset": true },
+            }))
+        );
+    }
+
+    #[test]
+    fn named_profile() {
+        assert_eq!(
+            profile(SKETCH_YAML, Some("nano")),
+            Some(json!({ "fqbn": "arduino:avr:nano" }))
+        );
+        assert_eq!(profile(SKETCH_YAML, Some("mega")), None);
+    }
+
+    #[test]
+    fn missing_default_profile() {
+        let sketch_yaml = "profiles:\n  uno:\n    fqbn: arduino:avr:uno\n";
+        assert_eq!(profile(sketch_yaml, None), None);
+        assert_eq!(
+            profile(sketch_yaml, Some("uno")),
+            Some(json!({ "fqbn": "arduino:avr:uno" }))
+        );
+    }
+
+    #[test]
+    fn top_level_values() {
+        let sketch_yaml =
+            "default_fqbn: arduino:avr:uno # on the bench\ndefault_port: '/dev/ttyACM0'\n";
+        assert_eq!(
+            top_level_value(sketch_yaml, "default_fqbn").as_deref(),
+            Some("arduino:avr:uno")
+        );
+        assert_eq!(
+            top_level_value(sketch_yaml, "default_port").as_deref(),
+            Some("/dev/ttyACM0")
+        );
+        assert_eq!(top_level_value(sketch_yaml, "default_programmer"), None);
+    }
+
+    #[test]
+    fn tabs_in_build_order() {
+        let tabs = |names: &[&str]| names.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            tab_order("Blink", tabs(&["motor.ino", "Blink.ino", "display.ino"])),
+            (
+                Some("Blink.ino".to_string()),
+                tabs(&["display.ino", "motor.ino"])
+            )
+        );
+        assert_eq!(
+            tab_order("Blink", tabs(&["sketch.ino", "helpers.ino"])),
+            (None, tabs(&["helpers.ino", "sketch.ino"]))
+        );
+    }
+}
@@ -0,0 +1,1593 @@
+use crate::{
+    cli::ArduinoCli,
+    host,
+    keywords::Keywords,
+    libraries, pinouts, platformio, reference,
+    settings::{ArduinoSettings, ResolvedSettings},
+    sketch,
+};
+use zed_extension_api::{
+    self as zed,
+    process::{Command, Output},
+    serde_json, SlashCommand, SlashCommandArgumentCompletion, SlashCommandOutput,
+    SlashCommandOutputSection,
+};
+
+/// Maximum number of search results inserted by the search subcommands.
+const MAX_SEARCH_RESULTS: usize = 25;
+
+pub fn run(
+    command: SlashCommand,
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    match command.name.as_str() {
+        "arduino-boards" => boards(worktree),
+        "arduino-lib" => lib(args, worktree),
+        "arduino-docs" => docs(args),
+        "arduino-example" => example(args, worktree),
+        "arduino-compile" => compile(args, worktree),
+        "arduino-pinout" => pinout(args, worktree),
+        "arduino-core" => core(args, worktree),
+        "arduino-new" => new_sketch(args, worktree),
+        "arduino-new-library" => new_library(args, worktree),
+        "arduino-fqbn" => fqbn(args, worktree),
+        "arduino-monitor" => monitor(args, worktree),
+        "arduino-size" => size(args, worktree),
+        "arduino-upgrade" => upgrade(args, worktree),
+        "arduino-outdated" => outdated(worktree),
+        "arduino-migrate" => migrate(args, worktree),
+        "arduino-keywords" => keywords(args, worktree),
+        command => Err(format!("unknown slash command: \"{command}\"")),
+    }
+}
+
+pub fn complete(
+    command: SlashCommand,
+    args: Vec<String>,
+) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+    let (subcommand, query) = split_subcommand(&args);
+    match (command.name.as_str(), args.len()) {
+        ("arduino-lib", 0 | 1) => Ok(keyword_completions(
+            &["search", "install", "show"],
+            subcommand,
+        )),
+        ("arduino-lib", _) if subcommand == "install" => library_completions(&query),
+        ("arduino-lib", _) if subcommand == "show" => installed_library_completions(&query),
+        ("arduino-core", 0 | 1) => Ok(keyword_completions(
+            &["list", "search", "install"],
+            subcommand,
+        )),
+        ("arduino-core", _) if subcommand == "install" => platform_completions(&query),
+        ("arduino-pinout", 0 | 1) => fqbn_completions(subcommand),
+        ("arduino-new", 2) => fqbn_completions(&query),
+        ("arduino-fqbn", 0 | 1) => fqbn_completions(subcommand),
+        ("arduino-upgrade", _) => Ok(keyword_completions(
+            &["index", "cores", "libraries"],
+            args.last().map_or("", String::as_str),
+        )),
+        _ => Ok(Vec::new()),
+    }
+}
+
+fn keyword_completions(keywords: &[&str], prefix: &str) -> Vec<SlashCommandArgumentCompletion> {
+    keywords
+        .iter()
+        .filter(|keyword| keyword.starts_with(prefix))
+        .map(|keyword| SlashCommandArgumentCompletion {
+            label: keyword.to_string(),
+            new_text: keyword.to_string(),
+            run_command: *keyword == "list",
+        })
+        .collect()
+}
+
+/// Completes library names from the Library Manager index.
+fn library_completions(query: &str) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let cli = ArduinoCli::for_worktree(None)?;
+    let results = cli.run_json(&["lib", "search", query])?;
+    Ok(results["libraries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .take(MAX_SEARCH_RESULTS)
+        .map(|library| {
+            let name = json_str(&library["name"]);
+            SlashCommandArgumentCompletion {
+                label: name.clone(),
+                new_text: name,
+                run_command: true,
+            }
+        })
+        .collect())
+}
+
+/// Completes the names of the installed libraries.
+fn installed_library_completions(
+    query: &str,
+) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+    let cli = ArduinoCli::for_worktree(None)?;
+    let query = query.to_lowercase();
+    let mut names: Vec<String> = libraries::installed(&cli)?
+        .into_iter()
+        .map(|library| library.name)
+        .filter(|name| name.to_lowercase().contains(&query))
+        .collect();
+    names.sort();
+    names.dedup();
+    Ok(names
+        .into_iter()
+        .map(|name| SlashCommandArgumentCompletion {
+            label: name.clone(),
+            new_text: name,
+            run_command: true,
+        })
+        .collect())
+}
+
+/// Completes platform IDs from the platform index.
+fn platform_completions(query: &str) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+    let cli = ArduinoCli::for_worktree(None)?;
+    let platforms = cli.run_json(&["core", "search", query])?;
+    Ok(platform_rows(&platforms)
+        .into_iter()
+        .take(MAX_SEARCH_RESULTS)
+        .map(|row| SlashCommandArgumentCompletion {
+            label: format!("{} ({})", row[0], row[3]),
+            new_text: row[0].clone(),
+            run_command: true,
+        })
+        .collect())
+}
+
+/// Completes the FQBNs of the boards of all installed platforms.
+fn fqbn_completions(query: &str) -> Result<Vec<SlashCommandArgumentCompletion>, String> {
+    let cli = ArduinoCli::for_worktree(None)?;
+    let installed = cli.run_json(&["board", "listall"])?;
+    let query = query.to_lowercase();
+    Ok(installed["boards"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|board| {
+            json_str(&board["fqbn"]).to_lowercase().contains(&query)
+                || json_str(&board["name"]).to_lowercase().contains(&query)
+        })
+        .map(|board| {
+            let fqbn = json_str(&board["fqbn"]);
+            SlashCommandArgumentCompletion {
+                label: format!("{fqbn} ({})", json_str(&board["name"])),
+                new_text: fqbn,
+                run_command: false,
+            }
+        })
+        .collect())
+}
+
+/// Lists the connected boards and the boards of all installed platforms.
+fn boards(worktree: Option<&zed::Worktree>) -> Result<SlashCommandOutput, String> {
+    let cli = ArduinoCli::for_worktree(worktree)?;
+
+    let detected = cli.run_json(&["board", "list"])?;
+    // Newer arduino-cli versions wrap the list in a `detected_ports` object
+    let detected_ports = detected.get("detected_ports").unwrap_or(&detected);
+    let mut connected = Vec::new();
+    for detected_port in detected_ports.as_array().into_iter().flatten() {
+        let port = &detected_port["port"];
+        let address = json_str(&port["address"]);
+        let protocol = json_str(&port["protocol_label"]);
+        let boards = detected_port["matching_boards"]
+            .as_array()
+            .filter(|boards| !boards.is_empty());
+        let Some(boards) = boards else {
+            connected.push(vec![
+                address,
+                protocol,
+                "Unknown".to_string(),
+                String::new(),
+            ]);
+            continue;
+        };
+        for board in boards {
+            connected.push(vec![
+                address.clone(),
+                protocol.clone(),
+                json_str(&board["name"]),
+                json_str(&board["fqbn"]),
+            ]);
+        }
+    }
+
+    let installed = cli.run_json(&["board", "listall"])?;
+    let mut installed_boards: Vec<Vec<String>> = installed["boards"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|board| vec![json_str(&board["name"]), json_str(&board["fqbn"])])
+        .collect();
+    installed_boards.sort();
+
+    let mut output = OutputBuilder::default();
+    output.section("Connected boards", |text| {
+        if connected.is_empty() {
+            text.push_str("No boards are connected.\n");
+        } else {
+            text.push_str(&markdown_table(
+                &["Port", "Protocol", "Board", "FQBN"],
+                &connected,
+            ));
+        }
+    });
+    output.section("Installed boards", |text| {
+        if installed_boards.is_empty() {
+            text.push_str("No platforms are installed.\n");
+        } else {
+            text.push_str(&markdown_table(&["Board", "FQBN"], &installed_boards));
+        }
+    });
+    Ok(output.build())
+}
+
+/// Searches for and installs libraries.
+fn lib(args: Vec<String>, worktree: Option<&zed::Worktree>) -> Result<SlashCommandOutput, String> {
+    let (subcommand, query) = split_subcommand(&args);
+    let cli = ArduinoCli::for_worktree(worktree)?;
+
+    let mut output = OutputBuilder::default();
+    match subcommand {
+        "search" if !query.is_empty() => {
+            let results = cli.run_json(&["lib", "search", &query])?;
+            let libraries = results["libraries"].as_array().cloned().unwrap_or_default();
+            let rows: Vec<Vec<String>> = libraries
+                .iter()
+                .take(MAX_SEARCH_RESULTS)
+                .map(|library| {
+                    let latest = &library["latest"];
+                    vec![
+                        json_str(&library["name"]),
+                        json_str(&latest["version"]),
+                        json_str(&latest["author"]),
+                        json_str(&latest["sentence"]),
+                    ]
+                })
+                .collect();
+
+            output.section(&format!("Libraries matching \"{query}\""), |text| {
+                if rows.is_empty() {
+                    text.push_str("No libraries found.\n");
+                    return;
+                }
+                text.push_str(&markdown_table(
+                    &["Name", "Version", "Author", "Description"],
+                    &rows,
+                ));
+                if libraries.len() > rows.len() {
+                    text.push_str(&format!(
+                        "\n{} more results omitted, refine the search to see them.\n",
+                        libraries.len() - rows.len()
+                    ));
+                }
+            });
+        }
+        "install" if !query.is_empty() => {
+            let log = cli.run(&["lib", "install", &query])?;
+            output.section(&format!("Installed {query}"), |text| {
+                text.push_str(&code_block(&log));
+            });
+        }
+        "show" if !query.is_empty() => {
+            let library = libraries::find(&cli, &query)?;
+            output.section(&format!("{} {}", library.name, library.version), |text| {
+                text.push_str(&format!("Installed in `{}`\n", library.install_dir));
+            });
+            if let Some((file_name, readme)) = library.readme() {
+                output.section(&file_name, |text| {
+                    text.push_str(readme.trim_end());
+                    text.push('\n');
+                });
+            }
+            for file_name in ["library.properties", "keywords.txt"] {
+                if let Some((_, contents)) = library.read_file(&[file_name]) {
+                    output.section(file_name, |text| text.push_str(&code_block(&contents)));
+                }
+            }
+        }
+        _ => {
+            return Err(
+                "usage: /arduino-lib search <term> | install <name>[@version] | show <name>"
+                    .to_string(),
+            )
+        }
+    }
+    Ok(output.build())
+}
+
+/// Inserts the Arduino language reference entry for a function or class.
+fn docs(args: Vec<String>) -> Result<SlashCommandOutput, String> {
+    let name = args.join(" ");
+    if name.is_empty() {
+        return Err("usage: /arduino-docs <function or Class.method>".to_string());
+    }
+
+    let entry = reference::lookup(&name)?;
+    let mut output = OutputBuilder::default();
+    output.section(&format!("Arduino reference: {name}"), |text| {
+        text.push_str(&format!("Reference: {}\n", entry.docs_url()));
+        text.push_str(&format!("Source: {}\n\n", entry.url()));
+        text.push_str(entry.content.trim_end());
+        text.push('\n');
+    });
+    Ok(output.build())
+}
+
+/// Inserts an example sketch of an installed library, or lists the examples
+/// when no example is given.
+fn example(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    if args.is_empty() {
+        return Err("usage: /arduino-example <library> [example]".to_string());
+    }
+    let cli = ArduinoCli::for_worktree(worktree)?;
+    let list_examples = |library: &str| -> Result<Vec<String>, String> {
+        let results = cli.run_json(&["lib", "examples", library])?;
+        Ok(results["examples"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(|library| library["examples"].as_array().into_iter().flatten())
+            .filter_map(|path| path.as_str().map(ToString::to_string))
+            .collect())
+    };
+
+    // Library names may contain spaces, so the last argument is first tried as
+    // the example name, falling back to all arguments naming the library
+    let split = args
+        .split_last()
+        .filter(|(_, library)| !library.is_empty())
+        .and_then(|(example, library)| {
+            let library = library.join(" ");
+            let examples = list_examples(&library).ok()?;
+            (!examples.is_empty()).then_some((library, Some(example.as_str()), examples))
+        });
+    let (library, example, examples) = match split {
+        Some(split) => split,
+        None => {
+            let library = args.join(" ");
+            let examples = list_examples(&library)?;
+            (library, None, examples)
+        }
+    };
+    if examples.is_empty() {
+        return Err(format!("no examples found for library `{library}`"));
+    }
+
+    let mut output = OutputBuilder::default();
+    let Some(example) = example else {
+        output.section(&format!("{library} examples"), |text| {
+            for path in &examples {
+                text.push_str(&format!("- {}\n", host::file_name(path)));
+            }
+        });
+        return Ok(output.build());
+    };
+
+    let path = examples
+        .iter()
+        .find(|path| host::file_name(path).eq_ignore_ascii_case(example))
+        .ok_or_else(|| format!("library `{library}` has no example named `{example}`"))?;
+    let name = host::file_name(path);
+    let sketch = host::read_text_file(&host::join(path, &format!("{name}.ino")))?;
+
+    output.section(&format!("{library}: {name}.ino"), |text| {
+        text.push_str(&format!("```cpp\n{}\n```\n", sketch.trim_end()));
+    });
+    Ok(output.build())
+}
+
+/// Compiles a sketch of the worktree and inserts the errors, if any.
+fn compile(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    let worktree = worktree.ok_or("/arduino-compile requires an open project")?;
+    let root = worktree.root_path();
+    let (sketch, result) = compile_sketch(worktree, &args, &[])?;
+
+    let mut output = OutputBuilder::default();
+    if result.status == Some(0) {
+        output.section("Compilation succeeded", |text| {
+            text.push_str(&format!("`{sketch}` compiled without errors.\n"));
+        });
+        return Ok(output.build());
+    }
+
+    let log = String::from_utf8_lossy(&result.stderr);
+    let errors = compiler_errors(&log, &root);
+    output.section("Compilation errors", |text| {
+        text.push_str(&code_block(&errors.join("\n")));
+    });
+    Ok(output.build())
+}
+
+/// Compiles the sketch named by `args` for the configured board, returning
+/// its path and arduino-cli's output.
+fn compile_sketch(
+    worktree: &zed::Worktree,
+    args: &[String],
+    extra_args: &[&str],
+) -> Result<(String, Output), String> {
+    let settings = ResolvedSettings::for_worktree(worktree)?;
+    let cli = ArduinoCli::new(worktree, &settings)?;
+    let sketch = sketch_path(&worktree.root_path(), args);
+
+    // Without an FQBN in the settings, arduino-cli takes it from sketch.yaml
+    let mut compile_args = vec!["compile"];
+    if let Some(fqbn) = &settings.arduino.fqbn {
+        compile_args.extend(["--fqbn", fqbn]);
+    }
+    compile_args.extend(extra_args);
+    compile_args.push(&sketch);
+    let result = cli.output(&compile_args)?;
+    Ok((sketch, result))
+}
+
+/// Compiles a sketch of the worktree and inserts how much flash and RAM it
+/// uses.
+fn size(args: Vec<String>, worktree: Option<&zed::Worktree>) -> Result<SlashCommandOutput, String> {
+    // The Arduino IDE warns about low memory above this share of RAM in use
+    const LOW_MEMORY_PERCENT: u64 = 75;
+
+    let worktree = worktree.ok_or("/arduino-size requires an open project")?;
+    let (sketch, result) = compile_sketch(worktree, &args, &["--format", "json"])?;
+    if result.status != Some(0) {
+        return Err(format!(
+            "`{sketch}` failed to compile, run /arduino-compile to see the errors"
+        ));
+    }
+    let build: serde_json::Value = serde_json::from_slice(&result.stdout)
+        .map_err(|e| format!("failed to parse arduino-cli output: {e}"))?;
+    // Older arduino-cli versions report the sizes at the top level
+    let sections = build
+        .get("builder_result")
+        .unwrap_or(&build)
+        .get("executable_sections_size")
+        .and_then(|sections| sections.as_array())
+        .filter(|sections| !sections.is_empty())
+        .ok_or("the board's platform doesn't report the size of the sketch")?;
+
+    let mut rows = Vec::new();
+    let mut warnings = Vec::new();
+    for section in sections {
+        let name = json_str(&section["name"]);
+        let used = section["size"].as_u64().unwrap_or_default();
+        let max = section["max_size"].as_u64().unwrap_or_default();
+        let label = match name.as_str() {
+            "text" => "Flash (program storage)".to_string(),
+            "data" => "RAM (global variables)".to_string(),
+            _ => name.clone(),
+        };
+        let percent = (max > 0).then(|| used * 100 / max);
+        rows.push(vec![
+            label,
+            format!("{used} bytes"),
+            if max > 0 {
+                format!("{max} bytes")
+            } else {
+                "-".to_string()
+            },
+            percent
+                .map(|percent| format!("{percent}%"))
+                .unwrap_or_default(),
+        ]);
+        match (name.as_str(), percent) {
+            (_, Some(percent)) if percent > 100 => {
+                warnings.push(format!("The `{name}` section is {percent}% of its maximum size."))
+            }
+            ("data", Some(percent)) if percent >= LOW_MEMORY_PERCENT => warnings.push(format!(
+                "Low memory available: global variables use {percent}% of RAM, leaving {} bytes for local variables and the heap.",
+                max.saturating_sub(used)
+            )),
+            _ => {}
+        }
+    }
+
+    let mut output = OutputBuilder::default();
+    output.section("Memory usage", |text| {
+        text.push_str(&markdown_table(
+            &["Section", "Used", "Maximum", "Usage"],
+            &rows,
+        ));
+        for warning in &warnings {
+            text.push_str(&format!("\n{warning}\n"));
+        }
+    });
+    Ok(output.build())
+}
+
+/// Returns the absolute path of the sketch named by `args`, relative to the
+/// worktree root, or the root itself when no sketch is given.
+fn sketch_path(root: &str, args: &[String]) -> String {
+    let sketch = args.join(" ");
+    if sketch.is_empty() {
+        root.to_string()
+    } else {
+        host::join(root, &sketch)
+    }
+}
+
+/// Extracts the distinct error lines from a compiler log, with paths made
+/// relative to the worktree root. Falls back to the tail of the log when no
+/// line looks like an error.
+fn compiler_errors(log: &str, root: &str) -> Vec<String> {
+    const TAIL_LINES: usize = 40;
+
+    let root_prefix = host::join(root, "");
+    let mut errors: Vec<String> = Vec::new();
+    for line in log.lines() {
+        let is_error = line.contains("error:")
+            || line.contains("undefined reference to")
+            || line.starts_with("Error ");
+        let line = line.replace(&root_prefix, "");
+        if is_error && !errors.contains(&line) {
+            errors.push(line);
+        }
+    }
+
+    if errors.is_empty() {
+        let lines: Vec<&str> = log.lines().collect();
+        let tail = &lines[lines.len().saturating_sub(TAIL_LINES)..];
+        errors = tail.iter().map(ToString::to_string).collect();
+    }
+    errors
+}
+
+/// Lists, searches for and installs platforms.
+fn core(args: Vec<String>, worktree: Option<&zed::Worktree>) -> Result<SlashCommandOutput, String> {
+    let (subcommand, query) = split_subcommand(&args);
+    let cli = ArduinoCli::for_worktree(worktree)?;
+
+    let mut output = OutputBuilder::default();
+    match subcommand {
+        "list" => {
+            let platforms = cli.run_json(&["core", "list"])?;
+            let rows = platform_rows(&platforms);
+            output.section("Installed platforms", |text| {
+                if rows.is_empty() {
+                    text.push_str("No platforms are installed.\n");
+                } else {
+                    text.push_str(&markdown_table(
+                        &["ID", "Installed", "Latest", "Name"],
+                        &rows,
+                    ));
+                }
+            });
+        }
+        "search" if !query.is_empty() => {
+            let platforms = cli.run_json(&["core", "search", &query])?;
+            let rows = platform_rows(&platforms);
+            output.section(&format!("Platforms matching \"{query}\""), |text| {
+                if rows.is_empty() {
+                    text.push_str("No platforms found. Platforms outside of the Arduino index need their package index URL in `board_manager.additional_urls` of arduino-cli's configuration.\n");
+                } else {
+                    text.push_str(&markdown_table(
+                        &["ID", "Installed", "Latest", "Name"],
+                        &rows,
+                    ));
+                }
+            });
+        }
+        "install" if !query.is_empty() => {
+            let log = cli.run(&["core", "install", &query])?;
+            output.section(&format!("Installed {query}"), |text| {
+                text.push_str(&code_block(&log));
+            });
+        }
+        _ => {
+            return Err(
+                "usage: /arduino-core list | search <term> | install <vendor:arch>[@version]"
+                    .to_string(),
+            )
+        }
+    }
+    Ok(output.build())
+}
+
+/// Returns the ID, installed version, latest version and name of each
+/// platform in the output of `core list` or `core search`.
+fn platform_rows(platforms: &serde_json::Value) -> Vec<Vec<String>> {
+    // Newer arduino-cli versions wrap the list in a `platforms` object and keep
+    // the names in the releases
+    let platforms = platforms.get("platforms").unwrap_or(platforms);
+    platforms
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|platform| {
+            let installed = platform
+                .get("installed_version")
+                .or_else(|| platform.get("installed"))
+                .map(json_str)
+                .unwrap_or_default();
+            let latest = platform
+                .get("latest_version")
+                .or_else(|| platform.get("latest"))
+                .map(json_str)
+                .unwrap_or_default();
+            let release = if installed.is_empty() {
+                &latest
+            } else {
+                &installed
+            };
+            let name = platform["releases"][release.as_str()]["name"]
+                .as_str()
+                .or_else(|| platform["name"].as_str())
+                .unwrap_or_default()
+                .to_string();
+            vec![json_str(&platform["id"]), installed, latest, name]
+        })
+        .collect()
+}
+
+/// Updates the package indexes and upgrades the installed platforms and
+/// libraries, or only the selected ones of these steps.
+fn upgrade(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    const STEPS: &[&str] = &["index", "cores", "libraries"];
+
+    if let Some(step) = args.iter().find(|arg| !STEPS.contains(&arg.as_str())) {
+        return Err(format!(
+            "unknown step `{step}`, usage: /arduino-upgrade [index] [cores] [libraries]"
+        ));
+    }
+    let selected = |step: &str| args.is_empty() || args.iter().any(|arg| arg == step);
+    let settings = worktree.map(ResolvedSettings::for_worktree).transpose()?;
+    let cli = match worktree.zip(settings.as_ref()) {
+        Some((worktree, settings)) => ArduinoCli::new(worktree, settings)?,
+        None => ArduinoCli::for_worktree(None)?,
+    };
+
+    let mut output = OutputBuilder::default();
+    if selected("index") {
+        let log = cli.run(&["update"])?;
+        output.section("Updated indexes", |text| text.push_str(&code_block(&log)));
+    }
+
+    let mut upgraded_platforms = Vec::new();
+    if selected("cores") {
+        let installed_platforms = || -> Result<Vec<(String, String)>, String> {
+            let platforms = cli.run_json(&["core", "list"])?;
+            Ok(platform_rows(&platforms)
+                .into_iter()
+                .map(|row| (row[0].clone(), row[1].clone()))
+                .collect())
+        };
+        let before = installed_platforms()?;
+        cli.run(&["core", "upgrade"])?;
+        upgraded_platforms = version_changes(&before, &installed_platforms()?);
+        output.section("Upgraded platforms", |text| {
+            if upgraded_platforms.is_empty() {
+                text.push_str("All platforms are up to date.\n");
+            } else {
+                text.push_str(&markdown_table(
+                    &["ID", "Previous", "Installed"],
+                    &upgraded_platforms,
+                ));
+            }
+        });
+    }
+
+    if selected("libraries") {
+        let installed_libraries = || -> Result<Vec<(String, String)>, String> {
+            Ok(libraries::installed(&cli)?
+                .into_iter()
+                .map(|library| (library.name, library.version))
+                .collect())
+        };
+        let before = installed_libraries()?;
+        cli.run(&["lib", "upgrade"])?;
+        let upgraded = version_changes(&before, &installed_libraries()?);
+        output.section("Upgraded libraries", |text| {
+            if upgraded.is_empty() {
+                text.push_str("All libraries are up to date.\n");
+            } else {
+                text.push_str(&markdown_table(
+                    &["Name", "Previous", "Installed"],
+                    &upgraded,
+                ));
+            }
+        });
+    }
+
+    // The language server keeps using the old core until it is restarted
+    let active_platform = worktree.zip(settings).and_then(|(worktree, settings)| {
+        let fqbn = sketch::active_fqbn(worktree, &settings.arduino)?;
+        let mut components = fqbn.split(':');
+        Some(format!("{}:{}", components.next()?, components.next()?))
+    });
+    if let Some(platform) =
+        active_platform.filter(|platform| upgraded_platforms.iter().any(|row| &row[0] == platform))
+    {
+        output.section("Restart needed", |text| {
+            text.push_str(&format!(
+                "The platform of the sketch's board, `{platform}`, was upgraded. Restart the language server (`editor: restart language server`) to use it.\n"
+            ));
+        });
+    }
+    Ok(output.build())
+}
+
+/// Lists the platforms and libraries with newer versions available, with the
+/// commands installing them.
+fn outdated(worktree: Option<&zed::Worktree>) -> Result<SlashCommandOutput, String> {
+    let cli = ArduinoCli::for_worktree(worktree)?;
+    let outdated = cli.run_json(&["outdated"])?;
+
+    let platforms: Vec<Vec<String>> = platform_rows(&outdated)
+        .into_iter()
+        .map(|row| {
+            let install = format!("`/arduino-core install {}@{}`", row[0], row[2]);
+            vec![row[0].clone(), row[1].clone(), row[2].clone(), install]
+        })
+        .collect();
+    let libraries: Vec<Vec<String>> = outdated["libraries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|library| {
+            let name = json_str(&library["library"]["name"]);
+            let latest = json_str(&library["release"]["version"]);
+            let install = format!("`/arduino-lib install {name}@{latest}`");
+            vec![
+                name,
+                json_str(&library["library"]["version"]),
+                latest,
+                install,
+            ]
+        })
+        .collect();
+
+    let mut output = OutputBuilder::default();
+    output.section("Outdated platforms", |text| {
+        if platforms.is_empty() {
+            text.push_str("All platforms are up to date.\n");
+        } else {
+            text.push_str(&markdown_table(
+                &["ID", "Installed", "Latest", "Upgrade with"],
+                &platforms,
+            ));
+        }
+    });
+    output.section("Outdated libraries", |text| {
+        if libraries.is_empty() {
+            text.push_str("All libraries are up to date.\n");
+        } else {
+            text.push_str(&markdown_table(
+                &["Name", "Installed", "Latest", "Upgrade with"],
+                &libraries,
+            ));
+        }
+    });
+    if !platforms.is_empty() || !libraries.is_empty() {
+        output.section("Upgrading everything", |text| {
+            text.push_str(
+                "Run `/arduino-upgrade cores libraries` to upgrade all of them at once.\n",
+            );
+        });
+    }
+    Ok(output.build())
+}
+
+/// Returns the name and the previous and current version of every entry
+/// whose version changed.
+fn version_changes(before: &[(String, String)], after: &[(String, String)]) -> Vec<Vec<String>> {
+    after
+        .iter()
+        .filter_map(|(name, version)| {
+            let previous = before
+                .iter()
+                .find(|(previous_name, _)| previous_name == name)
+                .map(|(_, previous)| previous.clone())
+                .unwrap_or_default();
+            (previous != *version).then(|| vec![name.clone(), previous, version.clone()])
+        })
+        .collect()
+}
+
+/// The `.ino` file of new sketches, unless the `sketch_template` setting
+/// replaces it.
+const DEFAULT_SKETCH_TEMPLATE: &str = "/*
+ * {name}
+ *
+ * Board: {board}
+ */
+
+void setup() {
+}
+
+void loop() {
+}
+";
+
+/// Creates a new sketch folder in the worktree, optionally attached to a
+/// board and with a `.gitignore`. The `.ino` file is filled from the
+/// `sketch_template` setting, for the given board or otherwise the active one.
+fn new_sketch(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    const USAGE: &str = "usage: /arduino-new <name> [fqbn] [--gitignore]";
+
+    let worktree = worktree.ok_or("/arduino-new requires an open project")?;
+    let gitignore = args.iter().any(|arg| arg == "--gitignore");
+    let mut positional = args.iter().filter(|arg| !arg.starts_with("--"));
+    let name = positional.next().ok_or(USAGE)?;
+    let fqbn = positional.next();
+    if positional.next().is_some() {
+        return Err(USAGE.to_string());
+    }
+
+    let settings = ResolvedSettings::for_worktree(worktree)?;
+    let cli = ArduinoCli::new(worktree, &settings)?;
+    let sketch = host::join(&worktree.root_path(), name);
+    cli.run(&["sketch", "new", &sketch])?;
+
+    let board_fqbn = fqbn
+        .cloned()
+        .or_else(|| sketch::active_fqbn(worktree, &settings.arduino));
+    let board = match &board_fqbn {
+        Some(board_fqbn) => match cli.run_json(&["board", "details", "--fqbn", board_fqbn]) {
+            Ok(details) => format!("{} ({board_fqbn})", json_str(&details["name"])),
+            Err(_) => board_fqbn.clone(),
+        },
+        None => "no board selected".to_string(),
+    };
+    let ino = host::join(&sketch, &format!("{name}.ino"));
+    let contents = settings
+        .arduino
+        .sketch_template
+        .as_deref()
+        .unwrap_or(DEFAULT_SKETCH_TEMPLATE)
+        .replace("{name}", name)
+        .replace("{fqbn}", board_fqbn.as_deref().unwrap_or_default())
+        .replace("{board}", &board);
+    host::write_text_file(&ino, &contents)?;
+
+    let mut created = vec![ino];
+    if let Some(fqbn) = fqbn {
+        // Attaching the board records it as the sketch's `default_fqbn`
+        cli.run(&["board", "attach", "--fqbn", fqbn, &sketch])?;
+        created.push(host::join(&sketch, "sketch.yaml"));
+    }
+    if gitignore {
+        let path = host::join(&sketch, ".gitignore");
+        host::write_text_file(&path, "build/\n")?;
+        created.push(path);
+    }
+
+    let mut output = OutputBuilder::default();
+    output.section(&format!("Created sketch {name}"), |text| {
+        for path in &created {
+            text.push_str(&format!("- {path}\n"));
+        }
+    });
+    Ok(output.build())
+}
+
+/// Creates the skeleton of a library in the worktree, with the files the
+/// Library Manager expects: `library.properties`, the sources in `src`, an
+/// example, `keywords.txt` and a license.
+fn new_library(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    const USAGE: &str = "usage: /arduino-new-library <Name>";
+
+    let worktree = worktree.ok_or("/arduino-new-library requires an open project")?;
+    let [name] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    // The name is also the one of the class and the header
+    let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_identifier {
+        return Err(format!(
+            "`{name}` must start with a letter and only contain letters, digits and underscores"
+        ));
+    }
+
+    // Sign the library like the user's commits, if git knows them
+    let git_config = |key: &str| {
+        let output = Command::new("git").args(["config", key]).output().ok()?;
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status == Some(0) && !value.is_empty()).then_some(value)
+    };
+    let author = match (git_config("user.name"), git_config("user.email")) {
+        (Some(name), Some(email)) => format!("{name} <{email}>"),
+        (Some(name), None) => name,
+        _ => "Your Name <you@example.com>".to_string(),
+    };
+
+    let guard = format!("{}_H", name.to_uppercase());
+    let object = name[..1].to_lowercase() + &name[1..];
+    let header = format!(
+        "#ifndef {guard}\n#define {guard}\n\n#include <Arduino.h>\n\nclass {name} {{\npublic:\n  {name}();\n  void begin();\n}};\n\n#endif\n"
+    );
+    let mut keywords = Keywords::default();
+    keywords.scan_header(&header);
+
+    let library = host::join(&worktree.root_path(), name);
+    let src = host::join(&library, "src");
+    let examples = host::join(&library, "examples");
+    let example = host::join(&examples, "Basic");
+    let files = [
+        (
+            host::join(&library, "library.properties"),
+            format!(
+                "name={name}\nversion=0.1.0\nauthor={author}\nmaintainer={author}\nsentence=A short description of {name}.\nparagraph=\ncategory=Uncategorized\nurl=\narchitectures=*\nincludes={name}.h\n"
+            ),
+        ),
+        (host::join(&src, &format!("{name}.h")), header),
+        (
+            host::join(&src, &format!("{name}.cpp")),
+            format!(
+                "#include \"{name}.h\"\n\n{name}::{name}() {{\n}}\n\nvoid {name}::begin() {{\n}}\n"
+            ),
+        ),
+        (
+            host::join(&example, "Basic.ino"),
+            format!(
+                "#include <{name}.h>\n\n{name} {object};\n\nvoid setup() {{\n  {object}.begin();\n}}\n\nvoid loop() {{\n}}\n"
+            ),
+        ),
+        (host::join(&library, "keywords.txt"), keywords.render(name)),
+        (
+            host::join(&library, "LICENSE"),
+            format!(
+                "Copyright (c) {author}\n\nReplace this file with the text of the library's license, such as one from https://choosealicense.com.\n"
+            ),
+        ),
+    ];
+
+    // Creating the library folder fails if it exists, so nothing is overwritten
+    for dir in [&library, &src, &examples, &example] {
+        host::create_dir(dir)?;
+    }
+    for (path, contents) in &files {
+        host::write_text_file(path, contents)?;
+    }
+
+    let mut output = OutputBuilder::default();
+    output.section(&format!("Created library {name}"), |text| {
+        for (path, _) in &files {
+            text.push_str(&format!("- {path}\n"));
+        }
+        text.push_str(
+            "\nFill in the `sentence`, `paragraph`, `category` and `url` of library.properties and choose a license before submitting the library to the Library Manager.\n",
+        );
+    });
+    Ok(output.build())
+}
+
+/// Sets the board of the worktree's sketch by recording it as the
+/// `default_fqbn` in sketch.yaml, or shows the active board without arguments.
+fn fqbn(args: Vec<String>, worktree: Option<&zed::Worktree>) -> Result<SlashCommandOutput, String> {
+    let worktree = worktree.ok_or("/arduino-fqbn requires an open project")?;
+    let settings = ResolvedSettings::for_worktree(worktree)?;
+
+    let mut output = OutputBuilder::default();
+    let Some(fqbn) = args.first() else {
+        let active = sketch::active_fqbn(worktree, &settings.arduino);
+        output.section("Active board", |text| match active {
+            Some(fqbn) => text.push_str(&format!("`{fqbn}`\n")),
+            None => text.push_str("No board selected.\n"),
+        });
+        return Ok(output.build());
+    };
+
+    // `board details` fails for unknown boards, uninstalled platforms and
+    // invalid board options alike
+    let cli = ArduinoCli::new(worktree, &settings)?;
+    let details = cli
+        .run_json(&["board", "details", "--fqbn", fqbn])
+        .map_err(|e| format!("`{fqbn}` is not a board of an installed platform: {e}"))?;
+    cli.run(&["board", "attach", "--fqbn", fqbn, &worktree.root_path()])?;
+
+    output.section("Active board", |text| {
+        text.push_str(&format!(
+            "Set the board of the sketch to {} (`{fqbn}`) in sketch.yaml.\n",
+            json_str(&details["name"])
+        ));
+        match &settings.arduino.fqbn {
+            Some(setting) if setting != fqbn => text.push_str(&format!(
+                "\nThe `fqbn` setting (`{setting}`) takes precedence over sketch.yaml, remove it to use this board.\n"
+            )),
+            _ => text.push_str(
+                "\nRestart the language server (`editor: restart language server`) to apply it.\n",
+            ),
+        }
+    });
+    Ok(output.build())
+}
+
+/// How long /arduino-monitor listens by default, and at most.
+const DEFAULT_MONITOR_SECONDS: u32 = 5;
+const MAX_MONITOR_SECONDS: u32 = 60;
+
+/// Captures the serial output of the board for a few seconds, or until it
+/// printed a number of lines.
+fn monitor(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    const USAGE: &str = "usage: /arduino-monitor [<seconds>s] [<lines>] [port]";
+
+    let mut seconds = None;
+    let mut lines = None;
+    let mut port = None;
+    for arg in &args {
+        if let Some(value) = arg.strip_suffix('s').and_then(|value| value.parse().ok()) {
+            seconds = Some(value);
+        } else if let Ok(value) = arg.parse::<usize>() {
+            lines = Some(value);
+        } else if port.is_none() {
+            port = Some(arg.clone());
+        } else {
+            return Err(USAGE.to_string());
+        }
+    }
+    // Waiting for a number of lines allows listening longer than by default
+    let seconds: u32 = seconds
+        .unwrap_or(if lines.is_some() {
+            MAX_MONITOR_SECONDS
+        } else {
+            DEFAULT_MONITOR_SECONDS
+        })
+        .min(MAX_MONITOR_SECONDS);
+
+    let (settings, cli) = match worktree {
+        Some(worktree) => {
+            let settings = ResolvedSettings::for_worktree(worktree)?;
+            let cli = ArduinoCli::new(worktree, &settings)?;
+            (settings.arduino, cli)
+        }
+        None => (ArduinoSettings::default(), ArduinoCli::for_worktree(None)?),
+    };
+    let port = match port.or_else(|| settings.port.clone()) {
+        Some(port) => port,
+        None => cli
+            .detected_port()?
+            .ok_or("no board connected, pass a port or set `port` in the arduino settings")?,
+    };
+
+    let mut monitor_args = vec!["monitor", "--port", &port, "--quiet"];
+    let port_config = settings.monitor.port_config();
+    for config in &port_config {
+        monitor_args.extend(["--config", config]);
+    }
+    let captured = cli.capture(&monitor_args, seconds, lines)?;
+
+    let mut output = OutputBuilder::default();
+    output.section(&format!("Serial output of {port}"), |text| {
+        if captured.trim().is_empty() {
+            text.push_str(&format!("No serial output within {seconds} seconds.\n"));
+        } else {
+            text.push_str(&code_block(&captured));
+        }
+    });
+    Ok(output.build())
+}
+
+/// Inserts the configuration the extension computed for the worktree: the
+/// command the language server was started with, the active board, the
+/// tools with their versions and the settings waiting for a restart, going
+/// by the command-line settings in `started_with`.
+pub fn config(
+    worktree: Option<&zed::Worktree>,
+    launched: Option<&zed::Command>,
+    started_with: Option<&serde_json::Value>,
+) -> Result<SlashCommandOutput, String> {
+    let worktree = worktree.ok_or("/arduino-config requires an open project")?;
+    let settings = ResolvedSettings::for_worktree(worktree);
+
+    let mut output = OutputBuilder::default();
+    output.section("Language server", |text| {
+        let Some(launched) = launched else {
+            text.push_str("The language server wasn't started for this project yet.\n");
+            return;
+        };
+        text.push_str(&code_block(&format!(
+            "{} {}",
+            launched.command,
+            launched.args.join(" ")
+        )));
+
+        // The environment holds the whole shell environment, so only the
+        // variables set through the settings are shown
+        let binary_env = settings
+            .as_ref()
+            .ok()
+            .and_then(|settings| settings.binary.as_ref())
+            .and_then(|binary| binary.env.clone())
+            .unwrap_or_default();
+        let mut env: Vec<Vec<String>> = launched
+            .env
+            .iter()
+            .filter(|(name, _)| {
+                name.starts_with("ARDUINO_")
+                    || name == "CLANGD_FLAGS"
+                    || binary_env.contains_key(name)
+            })
+            .map(|(name, value)| vec![name.clone(), value.clone()])
+            .collect();
+        env.sort();
+        if !env.is_empty() {
+            text.push('\n');
+            text.push_str(&markdown_table(&["Variable", "Value"], &env));
+        }
+    });
+
+    let settings = match settings {
+        Ok(settings) => settings,
+        Err(error) => {
+            output.section("Invalid settings", |text| {
+                text.push_str(&format!("{error}\n"));
+            });
+            return Ok(output.build());
+        }
+    };
+
+    let launched_flag = |flag: &str| {
+        let args = &launched?.args;
+        let index = args.iter().rposition(|arg| arg == flag)?;
+        args.get(index + 1).cloned()
+    };
+    let fqbn_source = if settings.arduino.fqbn.is_some() {
+        "settings"
+    } else {
+        "sketch.yaml"
+    };
+    let board = match sketch::active_fqbn(worktree, &settings.arduino) {
+        Some(fqbn) => format!("`{fqbn}` (from {fqbn_source})"),
+        None => "none".to_string(),
+    };
+    let cli_config = launched_flag("-cli-config").unwrap_or_else(|| "none".to_string());
+    let mut rows = vec![
+        vec!["FQBN".to_string(), board],
+        vec![
+            "Port".to_string(),
+            settings
+                .arduino
+                .port
+                .clone()
+                .unwrap_or_else(|| "none".to_string()),
+        ],
+        vec!["arduino-cli configuration".to_string(), cli_config],
+    ];
+
+    match ArduinoCli::new(worktree, &settings) {
+        Ok(cli) => {
+            let version = cli
+                .run_json(&["version"])
+                .map(|version| json_str(&version["VersionString"]))
+                .unwrap_or_else(|e| format!("unknown ({e})"));
+            rows.push(vec!["arduino-cli".to_string(), cli.path().to_string()]);
+            rows.push(vec!["arduino-cli version".to_string(), version]);
+        }
+        Err(error) => rows.push(vec!["arduino-cli".to_string(), error]),
+    }
+
+    let clangd = launched_flag("-clangd")
+        .or_else(|| settings.arduino.clangd_path.clone())
+        .or_else(|| host::which(worktree, "clangd"));
+    match clangd {
+        Some(clangd) => {
+            let version = Command::new(&clangd)
+                .arg("--version")
+                .output()
+                .inspect_err(|_| host::forget_tool(&clangd))
+                .ok()
+                .and_then(|output| {
+                    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+                    stdout.lines().next().map(ToString::to_string)
+                })
+                .unwrap_or_else(|| "unknown".to_string());
+            rows.push(vec!["clangd".to_string(), clangd]);
+            rows.push(vec!["clangd version".to_string(), version]);
+        }
+        None => rows.push(vec!["clangd".to_string(), "not found".to_string()]),
+    }
+
+    output.section("Configuration", |text| {
+        text.push_str(&markdown_table(&["Setting", "Value"], &rows));
+    });
+
+    // The language server maps positions between the tabs and the file it
+    // concatenates them into, which only works for a well-formed sketch
+    let tabs = sketch::tabs(worktree);
+    output.section("Sketch tabs", |text| {
+        let folder = host::file_name(&worktree.root_path()).to_string();
+        match &tabs {
+            Err(error) => text.push_str(&format!("{error}\n")),
+            Ok((Some(main), tabs)) => {
+                let rows: Vec<Vec<String>> = std::iter::once(main)
+                    .chain(tabs)
+                    .enumerate()
+                    .map(|(index, tab)| vec![(index + 1).to_string(), tab.clone()])
+                    .collect();
+                text.push_str(&markdown_table(&["Order", "Tab"], &rows));
+            }
+            Ok((None, tabs)) if tabs.is_empty() => text.push_str(
+                "The project root has no `.ino` file. The language server treats the project root as the sketch, so open the sketch folder itself as the project.\n"
+            ),
+            Ok((None, tabs)) => text.push_str(&format!(
+                "The project root has no `{folder}.ino`, which arduino-cli requires as the main file of a sketch in the `{folder}` folder, so the language server can't build the sketch and navigation between {} fails. Rename the main tab to `{folder}.ino`.\n",
+                tabs.iter().map(|tab| format!("`{tab}`")).collect::<Vec<_>>().join(", ")
+            )),
+        }
+    });
+
+    let changed = started_with
+        .map(|started_with| settings.changed_since(started_with))
+        .unwrap_or_default();
+    if !changed.is_empty() {
+        output.section("Restart needed", |text| {
+            text.push_str(&format!(
+                "These settings changed since the language server started and only apply once it is restarted (`editor: restart language server`): {}.\n",
+                changed
+                    .iter()
+                    .map(|key| format!("`{key}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        });
+    }
+    Ok(output.build())
+}
+
+/// Summarizes a PlatformIO project and proposes the sketch.yaml replacing
+/// its `platformio.ini`.
+fn migrate(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    let worktree = worktree.ok_or("/arduino-migrate requires an open project")?;
+    let path = match args.join(" ") {
+        path if path.is_empty() => "platformio.ini".to_string(),
+        path => path,
+    };
+    let ini = worktree
+        .read_text_file(&path)
+        .map_err(|e| format!("failed to read {path}: {e}"))?;
+    let project = platformio::Project::parse(&ini);
+    if project.environments.is_empty() {
+        return Err(format!("{path} has no `[env:...]` sections"));
+    }
+    let cli = ArduinoCli::for_worktree(Some(worktree))?;
+
+    let mut fqbns = Vec::new();
+    let mut environments = Vec::new();
+    for env in &project.environments {
+        let fqbn = match &env.board {
+            Some(board) => platformio::board_fqbn(board)?,
+            None => None,
+        };
+        environments.push(vec![
+            env.name.clone(),
+            env.board.clone().unwrap_or_default(),
+            fqbn.clone().unwrap_or_else(|| "unknown".to_string()),
+            env.platform.clone().unwrap_or_default(),
+            env.framework.clone().unwrap_or_default(),
+        ]);
+        fqbns.push(fqbn);
+    }
+
+    // Look up every dependency once in the Library Manager
+    let mut libraries: Vec<(String, Option<(String, String)>)> = Vec::new();
+    for lib_dep in project.environments.iter().flat_map(|env| &env.lib_deps) {
+        if libraries.iter().any(|(known, _)| known == lib_dep) {
+            continue;
+        }
+        let library = match platformio::library_name(lib_dep) {
+            Some(name) => {
+                let results = cli.run_json(&["lib", "search", name])?;
+                results["libraries"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|library| json_str(&library["name"]).eq_ignore_ascii_case(name))
+                    .map(|library| {
+                        (
+                            json_str(&library["name"]),
+                            json_str(&library["latest"]["version"]),
+                        )
+                    })
+            }
+            None => None,
+        };
+        libraries.push((lib_dep.clone(), library));
+    }
+
+    let installed_platforms = platform_rows(&cli.run_json(&["core", "list"])?);
+    let platform_version = |fqbn: &str| {
+        let platform = fqbn.split(':').take(2).collect::<Vec<_>>().join(":");
+        let version = installed_platforms
+            .iter()
+            .find(|row| row[0] == platform)
+            .map(|row| row[1].clone());
+        (platform, version)
+    };
+
+    let mut sketch_yaml = String::from("profiles:\n");
+    for (env, fqbn) in project.environments.iter().zip(&fqbns) {
+        let Some(fqbn) = fqbn else {
+            continue;
+        };
+        sketch_yaml.push_str(&format!("  {}:\n    fqbn: {fqbn}\n", env.name));
+        let (platform, version) = platform_version(fqbn);
+        sketch_yaml.push_str("    platforms:\n");
+        match version {
+            Some(version) => {
+                sketch_yaml.push_str(&format!("      - platform: {platform} ({version})\n"))
+            }
+            None => sketch_yaml.push_str(&format!(
+                "      - platform: {platform} # not installed, add its version\n"
+            )),
+        }
+        let env_libraries: Vec<_> = env
+            .lib_deps
+            .iter()
+            .filter_map(|lib_dep| {
+                libraries
+                    .iter()
+                    .find(|(known, _)| known == lib_dep)
+                    .and_then(|(_, library)| library.as_ref())
+            })
+            .collect();
+        if !env_libraries.is_empty() {
+            sketch_yaml.push_str("    libraries:\n");
+            for (name, version) in env_libraries {
+                sketch_yaml.push_str(&format!("      - {name} ({version})\n"));
+            }
+        }
+    }
+    let default_env = project.default_environment();
+    if let Some((env, Some(fqbn))) = default_env.and_then(|default_env| {
+        let index = project
+            .environments
+            .iter()
+            .position(|env| env.name == default_env.name)?;
+        Some((default_env, fqbns[index].as_ref()))
+    }) {
+        sketch_yaml.push_str(&format!(
+            "default_profile: {}\ndefault_fqbn: {fqbn}\n",
+            env.name
+        ));
+        if let Some(port) = &env.upload_port {
+            sketch_yaml.push_str(&format!("default_port: {port}\n"));
+        }
+    }
+
+    let mut output = OutputBuilder::default();
+    output.section("PlatformIO environments", |text| {
+        text.push_str(&markdown_table(
+            &["Environment", "Board", "FQBN", "Platform", "Framework"],
+            &environments,
+        ));
+        if fqbns.iter().any(Option::is_none) {
+            text.push_str("\nBoards marked unknown need their FQBN looked up, e.g. with `/arduino-boards`.\n");
+        }
+        if project
+            .environments
+            .iter()
+            .any(|env| env.framework.as_deref().is_some_and(|framework| !framework.contains("arduino")))
+        {
+            text.push_str("\nEnvironments that don't use the `arduino` framework can't be built with arduino-cli.\n");
+        }
+    });
+    output.section("Libraries", |text| {
+        if libraries.is_empty() {
+            text.push_str("The project has no `lib_deps`.\n");
+            return;
+        }
+        let rows: Vec<Vec<String>> = libraries
+            .iter()
+            .map(|(lib_dep, library)| match library {
+                Some((name, version)) => vec![lib_dep.clone(), name.clone(), version.clone()],
+                None => vec![lib_dep.clone(), "not found".to_string(), String::new()],
+            })
+            .collect();
+        text.push_str(&markdown_table(
+            &["lib_deps entry", "Library Manager name", "Latest version"],
+            &rows,
+        ));
+    });
+    output.section("Proposed sketch.yaml", |text| {
+        text.push_str(&format!("```yaml\n{sketch_yaml}```\n"));
+        text.push_str("\nThe library versions are the latest ones, pin the ones `platformio.ini` requires instead where they differ.");
+        if let Some(speed) = default_env.and_then(|env| env.monitor_speed.as_ref()) {
+            text.push_str(&format!(
+                " Set `monitor.baud` to {speed} in the arduino settings to keep the monitor speed."
+            ));
+        }
+        text.push('\n');
+    });
+    Ok(output.build())
+}
+
+/// Inserts the pin mapping of the given board, or of the active one.
+fn pinout(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    let fqbn = match (args.first(), worktree) {
+        (Some(fqbn), _) => fqbn.clone(),
+        (None, Some(worktree)) => {
+            let settings = ResolvedSettings::for_worktree(worktree)?;
+            sketch::active_fqbn(worktree, &settings.arduino).ok_or(
+                "no board selected, pass an FQBN or set `fqbn` in the settings or sketch.yaml",
+            )?
+        }
+        (None, None) => return Err("usage: /arduino-pinout <fqbn>".to_string()),
+    };
+
+    let board_id = sketch::board_id(&fqbn);
+    let board =
+        pinouts::board(&fqbn)?.ok_or_else(|| format!("no pinout available for `{board_id}`"))?;
+    let rows: Vec<Vec<String>> = board
+        .pins
+        .iter()
+        .map(|(function, pins)| vec![function.clone(), pins.clone()])
+        .collect();
+
+    let mut output = OutputBuilder::default();
+    output.section(&format!("{} pinout", board.name), |text| {
+        text.push_str(&markdown_table(&["Function", "Pins"], &rows));
+    });
+    Ok(output.build())
+}
+
+/// Generates the `keywords.txt` of a library in the project from its public
+/// headers, keeping the entries of an existing one. `--write` writes it to
+/// the library folder.
+fn keywords(
+    args: Vec<String>,
+    worktree: Option<&zed::Worktree>,
+) -> Result<SlashCommandOutput, String> {
+    let worktree = worktree.ok_or("/arduino-keywords requires an open project")?;
+    let write = args.iter().any(|arg| arg == "--write");
+    let folder: Vec<&str> = args
+        .iter()
+        .filter(|arg| !arg.starts_with("--"))
+        .map(String::as_str)
+        .collect();
+    let library = match folder.join(" ") {
+        folder if folder.is_empty() => worktree.root_path(),
+        folder => host::join(&worktree.root_path(), &folder),
+    };
+
+    // Libraries in the 1.5 format keep their sources in `src`
+    let src = host::join(&library, "src");
+    let mut headers = Vec::new();
+    for dir in [&src, &library] {
+        for extension in ["h", "hpp"] {
+            headers.extend(
+                host::list_files(dir, extension)?
+                    .into_iter()
+                    .filter(|path| {
+                        path.strip_suffix(host::file_name(path))
+                            .is_some_and(|parent| {
+                                parent.trim_end_matches(['/', '\\']) == dir.as_str()
+                            })
+                    }),
+            );
+        }
+        if !headers.is_empty() {
+            break;
+        }
+    }
+    if headers.is_empty() {
+        return Err(format!("no headers found in {library} or its src folder"));
+    }
+
+    let keywords_txt = host::join(&library, "keywords.txt");
+    let mut keywords = host::read_text_file(&keywords_txt)
+        .map(|existing| Keywords::parse(&existing))
+        .unwrap_or_default();
+    let existing = keywords.len();
+    for header in &headers {
+        keywords.scan_header(&host::read_text_file(header)?);
+    }
+
+    let name = host::read_text_file(&host::join(&library, "library.properties"))
+        .ok()
+        .and_then(|properties| {
+            properties.lines().find_map(|line| {
+                line.strip_prefix("name=")
+                    .map(|name| name.trim().to_string())
+            })
+        })
+        .unwrap_or_else(|| host::file_name(&library).to_string());
+    let rendered = keywords.render(&name);
+
+    let mut output = OutputBuilder::default();
+    output.section(&format!("keywords.txt of {name}"), |text| {
+        text.push_str(&format!(
+            "{} keywords from {} headers, {} new.\n\n",
+            keywords.len(),
+            headers.len(),
+            keywords.len() - existing
+        ));
+        text.push_str(&code_block(&rendered));
+    });
+    if write {
+        host::write_text_file(&keywords_txt, &rendered)?;
+        output.section("Written", |text| {
+            text.push_str(&format!("- {keywords_txt}\n"));
+        });
+    }
+    Ok(output.build())
+}
+
+/// Splits slash command arguments into the subcommand and the rest of the
+/// arguments, joined back into a single string.
+fn split_subcommand(args: &[String]) -> (&str, String) {
+    match args.split_first() {
+        Some((subcommand, rest)) => (subcommand.as_str(), rest.join(" ")),
+        None => ("", String::new()),
+    }
+}
+
+/// Accumulates slash command output, keeping track of the labeled sections.
+#[derive(Default)]
+struct OutputBuilder {
+    text: String,
+    sections: Vec<SlashCommandOutputSection>,
+}
+
+impl OutputBuilder {
+    fn section(&mut self, label: &str, write: impl FnOnce(&mut String)) {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        let start = self.text.len();
+        self.text.push_str(&format!("## {label}\n\n"));
+        write(&mut self.text);
+        self.sections.push(SlashCommandOutputSection {
+            range: (start..self.text.len()).into(),
+            label: label.to_string(),
+        });
+    }
+
+    fn build(self) -> SlashCommandOutput {
+        SlashCommandOutput {
+            text: self.text,
+            sections: self.sections,
+        }
+    }
+}
+
+fn markdown_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    let mut table = format!("| {} |\n", headers.join(" | "));
+    table.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| escape(cell)).collect();
+        table.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    table
+}
+
+fn code_block(text: &str) -> String {
+    format!("```\n{}\n```\n", text.trim_end())
+}
+
+fn json_str(value: &serde_json::Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
@@ -0,0 +1,238 @@
+use crate::{host, now, UPDATE_CHECK_INTERVAL};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, fs, io::Read};
+use zed_extension_api::{self as zed, process::Command, serde_json, GithubRelease, Result};
+
+/// File inside the extension's working directory recording the installed
+/// releases of the tools by repository, so debugging works offline and
+/// doesn't ask GitHub every time.
+const INSTALL_STATES: &str = "debug-tools.json";
+
+/// A debug tool installed from the GitHub releases of its distribution into
+/// the extension's working directory.
+pub struct Tool<'a> {
+    pub name: &'a str,
+    pub repository: &'a str,
+    /// Prefix of the directories the releases are extracted to, followed by
+    /// their version.
+    pub dir_prefix: &'a str,
+}
+
+/// A release of a [`Tool`] extracted to `dir`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Installed {
+    pub dir: String,
+    pub version: String,
+}
+
+/// The installed release of a tool, and when it was last checked against
+/// GitHub.
+#[derive(Serialize, Deserialize)]
+struct InstallState {
+    installed: Installed,
+    checked_at: u64,
+}
+
+/// Installs the latest release of a tool, unless `executable` already exists
+/// in it, and removes the releases installed before.
+///
+/// Like the language server, an installed release is used without checking
+/// for a newer one for a day, and when GitHub can't be reached.
+///
+/// `asset_name` names the archive of a version for the current platform, and
+/// `executable` the path of the tool inside the extracted release. Archives
+/// are checked against the SHA-256 checksums the release publishes.
+pub fn install(
+    tool: &Tool,
+    asset_name: impl Fn(&str) -> String,
+    executable: impl Fn(&Installed) -> String,
+) -> Result<Installed> {
+    let is_installed = |installed: &Installed| {
+        fs::metadata(executable(installed)).is_ok_and(|stat| stat.is_file())
+    };
+    let mut states = read_install_states();
+    let recorded = states
+        .remove(tool.repository)
+        .filter(|state| is_installed(&state.installed));
+    if let Some(state) = &recorded {
+        if now().saturating_sub(state.checked_at) < UPDATE_CHECK_INTERVAL {
+            return Ok(state.installed.clone());
+        }
+    }
+
+    let release = match zed::latest_github_release(
+        tool.repository,
+        zed::GithubReleaseOptions {
+            require_assets: true,
+            pre_release: false,
+        },
+    ) {
+        Ok(release) => release,
+        Err(e) => match recorded {
+            Some(state) => {
+                eprintln!("arduino: failed to check for {} updates: {e}", tool.name);
+                return Ok(state.installed);
+            }
+            None => return Err(e),
+        },
+    };
+    let version = release.version.trim_start_matches('v').to_string();
+    let installed = Installed {
+        dir: format!("{}{version}", tool.dir_prefix),
+        version,
+    };
+    if is_installed(&installed) {
+        record_install_state(tool, &installed);
+        return Ok(installed);
+    }
+
+    let asset_name = asset_name(&installed.version);
+    let asset = release
+        .assets
+        .iter()
+        .find(|asset| asset.name == asset_name)
+        .ok_or_else(|| format!("no asset found matching {asset_name:?}"))?;
+
+    // Archives are extracted by hand, to check them before
+    let archive = format!("{}.download", installed.dir);
+    zed::download_file(
+        &asset.download_url,
+        &archive,
+        zed::DownloadedFileType::Uncompressed,
+    )
+    .map_err(|e| format!("failed to download {}: {e}", tool.name))?;
+    let extracted =
+        verify(&release, &asset_name, &archive).and_then(|()| extract(&archive, &installed.dir));
+    fs::remove_file(&archive).ok();
+    if let Err(e) = extracted {
+        fs::remove_dir_all(&installed.dir).ok();
+        return Err(format!("failed to install {}: {e}", tool.name));
+    }
+
+    // Remove the previously installed releases and unfinished downloads
+    let entries = fs::read_dir(".").map_err(|e| format!("failed to list working directory {e}"))?;
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(file_name) = file_name.to_str() else {
+            continue;
+        };
+        if file_name.starts_with(tool.dir_prefix) && file_name != installed.dir {
+            fs::remove_dir_all(entry.path())
+                .or_else(|_| fs::remove_file(entry.path()))
+                .ok();
+        }
+    }
+
+    zed::make_file_executable(&executable(&installed))?;
+    record_install_state(tool, &installed);
+    Ok(installed)
+}
+
+fn read_install_states() -> HashMap<String, InstallState> {
+    fs::read_to_string(INSTALL_STATES)
+        .ok()
+        .and_then(|states| serde_json::from_str(&states).ok())
+        .unwrap_or_default()
+}
+
+fn record_install_state(tool: &Tool, installed: &Installed) {
+    let mut states = read_install_states();
+    states.insert(
+        tool.repository.to_string(),
+        InstallState {
+            installed: installed.clone(),
+            checked_at: now(),
+        },
+    );
+    // Failing to record it only costs a check for updates next time
+    if let Ok(states) = serde_json::to_string(&states) {
+        fs::write(INSTALL_STATES, states).ok();
+    }
+}
+
+/// Checks a downloaded asset against the checksum its release publishes.
+fn verify(release: &GithubRelease, asset_name: &str, archive: &str) -> Result<()> {
+    let expected = published_checksum(release, asset_name)?
+        .ok_or_else(|| format!("the release publishes no checksum of {asset_name}"))?;
+
+    let mut file = fs::File::open(archive).map_err(|e| format!("failed to open {archive}: {e}"))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| format!("failed to read {archive}: {e}"))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    let actual: String = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    if !actual.eq_ignore_ascii_case(&expected) {
+        return Err(format!(
+            "checksum mismatch for {asset_name}: expected {expected}, got {actual}"
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the SHA-256 checksum of an asset, from a `<asset>.sha` file like
+/// xPack's, or from a list of checksums like Espressif's.
+fn published_checksum(release: &GithubRelease, asset_name: &str) -> Result<Option<String>> {
+    let checksum_assets = release.assets.iter().filter(|asset| {
+        asset.name == format!("{asset_name}.sha")
+            || asset.name == format!("{asset_name}.sha256")
+            || asset.name.ends_with("checksum.sha256")
+            || asset.name == "SHA256SUMS"
+    });
+    for checksum_asset in checksum_assets {
+        let path = format!("{}.download", checksum_asset.name);
+        zed::download_file(
+            &checksum_asset.download_url,
+            &path,
+            zed::DownloadedFileType::Uncompressed,
+        )
+        .map_err(|e| format!("failed to download {}: {e}", checksum_asset.name))?;
+        let checksums = fs::read_to_string(&path);
+        fs::remove_file(&path).ok();
+        let checksums =
+            checksums.map_err(|e| format!("failed to read {}: {e}", checksum_asset.name))?;
+
+        // Lines are `<checksum> <file name>`, with a `*` before binary files
+        let per_asset = checksum_asset.name.starts_with(asset_name);
+        let checksum = checksums.lines().find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let checksum = fields.next()?;
+            let file_name = fields.next().map(|name| name.trim_start_matches('*'));
+            (checksum.len() == 64 && (per_asset || file_name == Some(asset_name)))
+                .then(|| checksum.to_string())
+        });
+        if checksum.is_some() {
+            return Ok(checksum);
+        }
+    }
+    Ok(None)
+}
+
+/// Extracts a `.tar.gz` or `.zip` archive with the host's `tar`, which ships
+/// with Windows as well.
+fn extract(archive: &str, dir: &str) -> Result<()> {
+    fs::create_dir_all(dir).map_err(|e| format!("failed to create {dir} directory: {e}"))?;
+    let archive = host::work_dir_path(archive)?;
+    let dir = host::work_dir_path(dir)?;
+    let output = Command::new("tar")
+        .args(["-xf", &archive, "-C", &dir])
+        .output()
+        .map_err(|e| format!("failed to run tar: {e}"))?;
+    if output.status != Some(0) {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("failed to extract {archive}: {}", stderr.trim()));
+    }
+    Ok(())
+}